@@ -0,0 +1,7 @@
+-- Add migration script here
+-- Spell out the lifecycle of a subscription: a subscriber starts as
+-- `pending_confirmation`, becomes `confirmed` once they click the link
+-- in the welcome email and can leave the list at any point afterwards.
+ALTER TABLE subscriptions
+    ADD CONSTRAINT subscriptions_status_check
+    CHECK (status IN ('pending_confirmation', 'confirmed', 'unsubscribed'));
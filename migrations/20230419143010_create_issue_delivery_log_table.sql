@@ -0,0 +1,14 @@
+-- Add migration script here
+-- One row per (issue, subscriber), updated after every delivery attempt.
+CREATE TABLE issue_delivery_log (
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_email TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    provider_message_id TEXT NULL,
+    n_attempts INT NOT NULL,
+    last_error TEXT NULL,
+    first_attempted_at timestamptz NOT NULL,
+    last_attempted_at timestamptz NOT NULL,
+    PRIMARY KEY(newsletter_issue_id, subscriber_email)
+);
@@ -0,0 +1,10 @@
+-- Add migration script here
+-- An issue is either sent right away (`published`) or held back until
+-- `scheduled_for` (`scheduled`), in which case it can still be `cancelled`.
+-- `published_at` is only known once the issue has actually gone out.
+ALTER TABLE newsletter_issues
+    ADD COLUMN status TEXT NOT NULL DEFAULT 'published',
+    ADD COLUMN scheduled_for timestamptz NULL,
+    ALTER COLUMN published_at DROP NOT NULL,
+    ADD CONSTRAINT newsletter_issues_status_check
+    CHECK (status IN ('published', 'scheduled', 'cancelled'));
@@ -0,0 +1,9 @@
+-- Add migration script here
+-- A `draft` issue can be edited (and previewed) as many times as needed
+-- before being published or scheduled.
+ALTER TABLE newsletter_issues
+    DROP CONSTRAINT newsletter_issues_status_check,
+    ADD CONSTRAINT newsletter_issues_status_check
+    CHECK (status IN ('draft', 'published', 'scheduled', 'cancelled')),
+    ADD COLUMN created_at timestamptz NOT NULL DEFAULT now(),
+    ADD COLUMN updated_at timestamptz NOT NULL DEFAULT now();
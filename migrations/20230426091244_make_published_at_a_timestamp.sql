@@ -0,0 +1,9 @@
+-- Add migration script here
+-- `published_at` was stored as the text rendering of `now()`, which casts
+-- back losslessly.
+ALTER TABLE newsletter_issues
+    ALTER COLUMN published_at TYPE timestamptz USING published_at::timestamptz;
+-- Every published issue has a publication date.
+UPDATE newsletter_issues
+    SET published_at = created_at
+    WHERE status = 'published' AND published_at IS NULL;
@@ -0,0 +1,28 @@
+-- Add migration script here
+-- Open and click tracking are opt-in, issue by issue.
+ALTER TABLE newsletter_issues
+    ADD COLUMN track_opens BOOLEAN NOT NULL DEFAULT false,
+    ADD COLUMN track_clicks BOOLEAN NOT NULL DEFAULT false;
+
+-- One row per load of the tracking pixel.
+CREATE TABLE issue_open_events (
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id) ON DELETE CASCADE,
+    opened_at timestamptz NOT NULL
+);
+CREATE INDEX issue_open_events_issue_subscriber_idx
+    ON issue_open_events (newsletter_issue_id, subscriber_id);
+
+-- One row per followed link.
+CREATE TABLE issue_click_events (
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    clicked_at timestamptz NOT NULL
+);
+CREATE INDEX issue_click_events_issue_subscriber_idx
+    ON issue_click_events (newsletter_issue_id, subscriber_id);
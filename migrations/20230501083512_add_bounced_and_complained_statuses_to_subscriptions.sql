@@ -0,0 +1,9 @@
+-- Add migration script here
+-- Addresses that hard bounced (`bounced`) or flagged an issue as spam
+-- (`complained`) are never mailed again.
+ALTER TABLE subscriptions
+    DROP CONSTRAINT subscriptions_status_check,
+    ADD CONSTRAINT subscriptions_status_check
+    CHECK (status IN (
+        'pending_confirmation', 'confirmed', 'unsubscribed', 'bounced', 'complained'
+    ));
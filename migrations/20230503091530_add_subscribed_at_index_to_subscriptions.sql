@@ -0,0 +1,4 @@
+-- Add migration script here
+-- Backs the keyset pagination of the admin subscriber directory.
+CREATE INDEX subscriptions_subscribed_at_id_idx
+    ON subscriptions (subscribed_at DESC, id DESC);
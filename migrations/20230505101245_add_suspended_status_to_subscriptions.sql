@@ -0,0 +1,10 @@
+-- Add migration script here
+-- Admins can put a subscriber on hold (`suspended`) and confirm them again
+-- later on.
+ALTER TABLE subscriptions
+    DROP CONSTRAINT subscriptions_status_check,
+    ADD CONSTRAINT subscriptions_status_check
+    CHECK (status IN (
+        'pending_confirmation', 'confirmed', 'unsubscribed', 'bounced', 'complained',
+        'suspended'
+    ));
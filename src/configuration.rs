@@ -6,26 +6,59 @@ use sqlx::postgres::PgSslMode;
 use sqlx::ConnectOptions;
 
 use crate::domain::SubscriberEmail;
-use crate::email_client::EmailClient;
+use crate::email_client::{EmailClient, OutboxTransport, PostmarkTransport, SmtpTransport};
 
 #[derive(serde::Deserialize, Clone)]
 pub struct EmailClientSettings {
-    pub base_url: String,
+    pub transport: EmailTransportKind,
     pub sender_email: String,
-    pub authorization_token: Secret<String>,
     pub timeout_milliseconds: u64,
+    // Postmark
+    pub base_url: String,
+    pub authorization_token: Secret<String>,
+    /// Sent by Postmark in the `X-Postmark-Webhook-Secret` header of its
+    /// bounce and spam complaint webhooks.
+    pub webhook_secret: Secret<String>,
+    pub smtp: SmtpSettings,
+    /// Where `.eml` files are written when using the `outbox` transport.
+    pub outbox_directory: String,
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailTransportKind {
+    Postmark,
+    Smtp,
+    Outbox,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<Secret<String>>,
+    pub require_tls: bool,
 }
 
 impl EmailClientSettings {
     pub fn client(self) -> EmailClient {
         let sender_email = self.sender().expect("Invalid sender email address.");
         let timeout = self.timeout();
-        EmailClient::new(
-            self.base_url,
-            sender_email,
-            self.authorization_token,
-            timeout,
-        )
+        match self.transport {
+            EmailTransportKind::Postmark => EmailClient::new(
+                sender_email,
+                PostmarkTransport::new(self.base_url, self.authorization_token, timeout),
+            ),
+            EmailTransportKind::Smtp => EmailClient::new(
+                sender_email,
+                SmtpTransport::new(&self.smtp, timeout).expect("Invalid SMTP settings."),
+            ),
+            EmailTransportKind::Outbox => {
+                EmailClient::new(sender_email, OutboxTransport::new(self.outbox_directory))
+            }
+        }
     }
 
     pub fn sender(&self) -> Result<SubscriberEmail, String> {
@@ -77,6 +110,45 @@ pub struct Settings {
     pub application: ApplicationSettings,
     pub email_client: EmailClientSettings,
     pub redis_uri: Secret<String>,
+    pub issue_delivery: IssueDeliverySettings,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct IssueDeliverySettings {
+    /// How many times a failed delivery is retried before the task is
+    /// moved to the dead-letter table.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_retries: i32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub retry_base_delay_milliseconds: u64,
+    /// Workers are woken up by Postgres notifications when new tasks are
+    /// enqueued; polling only catches what notifications may have missed
+    /// (e.g. retries or scheduled issues coming due, or a dropped listener
+    /// connection).
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub poll_interval_seconds: u64,
+    /// How many workers process the delivery queue in parallel.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub concurrency: usize,
+    /// How many tasks a worker dequeues at once.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub batch_size: i64,
+    /// Cap on the emails sent per second across all workers, to stay
+    /// within the provider's quota.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_sends_per_second: u32,
+}
+
+impl IssueDeliverySettings {
+    /// Exponential backoff: the delay doubles with every failed attempt.
+    pub fn retry_delay(&self, n_retries: i32) -> std::time::Duration {
+        let factor = 2u32.saturating_pow(n_retries.max(0) as u32);
+        std::time::Duration::from_millis(self.retry_base_delay_milliseconds).saturating_mul(factor)
+    }
+
+    pub fn poll_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.poll_interval_seconds)
+    }
 }
 
 #[derive(serde::Deserialize, Clone)]
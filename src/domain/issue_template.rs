@@ -0,0 +1,165 @@
+/// A placeholder that gets replaced with subscriber details at send time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeTag {
+    Name,
+    Email,
+    UnsubscribeUrl,
+}
+
+impl MergeTag {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "name" => Some(Self::Name),
+            "email" => Some(Self::Email),
+            "unsubscribe_url" => Some(Self::UnsubscribeUrl),
+            _ => None,
+        }
+    }
+}
+
+/// What merge tags are replaced with for a given recipient.
+pub struct MergeFields<'a> {
+    pub name: &'a str,
+    pub email: &'a str,
+    pub unsubscribe_url: &'a str,
+}
+
+impl MergeFields<'_> {
+    fn get(&self, tag: MergeTag) -> &str {
+        match tag {
+            MergeTag::Name => self.name,
+            MergeTag::Email => self.email,
+            MergeTag::UnsubscribeUrl => self.unsubscribe_url,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Segment {
+    Literal(String),
+    Tag(MergeTag),
+}
+
+/// The content of an issue, with `{{ name }}`, `{{ email }}` and
+/// `{{ unsubscribe_url }}` merge tags.
+#[derive(Debug)]
+pub struct IssueTemplate(Vec<Segment>);
+
+impl IssueTemplate {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut segments = Vec::new();
+        let mut rest = s;
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                segments.push(Segment::Literal(rest[..start].to_string()));
+            }
+            let after_opening = &rest[start + 2..];
+            let end = after_opening
+                .find("}}")
+                .ok_or_else(|| "A merge tag is missing its closing }}.".to_string())?;
+            let name = after_opening[..end].trim();
+            let tag = MergeTag::parse(name).ok_or_else(|| {
+                format!(
+                    "{{{{ {} }}}} is not a known merge tag. \
+                    Use {{{{ name }}}}, {{{{ email }}}} or {{{{ unsubscribe_url }}}}.",
+                    name
+                )
+            })?;
+            segments.push(Segment::Tag(tag));
+            rest = &after_opening[end + 2..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_string()));
+        }
+        Ok(Self(segments))
+    }
+
+    /// Content without any merge tag, sent as is.
+    pub fn literal(s: &str) -> Self {
+        Self(vec![Segment::Literal(s.to_string())])
+    }
+
+    pub fn uses(&self, tag: MergeTag) -> bool {
+        self.0
+            .iter()
+            .any(|segment| matches!(segment, Segment::Tag(t) if *t == tag))
+    }
+
+    /// Merge fields are HTML-escaped.
+    pub fn render_html(&self, fields: &MergeFields) -> String {
+        self.render(|tag| htmlescape::encode_minimal(fields.get(tag)))
+    }
+
+    pub fn render_text(&self, fields: &MergeFields) -> String {
+        self.render(|tag| fields.get(tag).to_string())
+    }
+
+    fn render(&self, field: impl Fn(MergeTag) -> String) -> String {
+        self.0
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(s) => s.clone(),
+                Segment::Tag(tag) => field(*tag),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IssueTemplate, MergeFields, MergeTag};
+    use claims::{assert_err, assert_ok};
+
+    fn fields() -> MergeFields<'static> {
+        MergeFields {
+            name: "Ursula & co",
+            email: "ursula@example.com",
+            unsubscribe_url: "https://example.com/unsubscribe?token=abc",
+        }
+    }
+
+    #[test]
+    fn merge_tags_are_replaced_with_the_recipient_details() {
+        let template = assert_ok!(IssueTemplate::parse(
+            "Hi {{name}}, this is for {{ email }}. Bye: {{  unsubscribe_url }}"
+        ));
+        assert_eq!(
+            template.render_text(&fields()),
+            "Hi Ursula & co, this is for ursula@example.com. \
+            Bye: https://example.com/unsubscribe?token=abc"
+        );
+    }
+
+    #[test]
+    fn merge_fields_are_escaped_in_html() {
+        let template = assert_ok!(IssueTemplate::parse("<p>Hi {{ name }}</p>"));
+        assert_eq!(template.render_html(&fields()), "<p>Hi Ursula &amp; co</p>");
+    }
+
+    #[test]
+    fn content_without_merge_tags_is_left_untouched() {
+        let template = assert_ok!(IssueTemplate::parse("<style>p { color: red; }</style>"));
+        assert_eq!(
+            template.render_html(&fields()),
+            "<style>p { color: red; }</style>"
+        );
+        assert!(!template.uses(MergeTag::UnsubscribeUrl));
+    }
+
+    #[test]
+    fn used_merge_tags_are_reported() {
+        let template = assert_ok!(IssueTemplate::parse("<a href=\"{{ unsubscribe_url }}\">"));
+        assert!(template.uses(MergeTag::UnsubscribeUrl));
+        assert!(!template.uses(MergeTag::Name));
+    }
+
+    #[test]
+    fn unknown_merge_tags_are_rejected() {
+        assert_err!(IssueTemplate::parse("Hi {{ first_name }}"));
+    }
+
+    #[test]
+    fn unclosed_merge_tags_are_rejected() {
+        assert_err!(IssueTemplate::parse("Hi {{ name"));
+    }
+}
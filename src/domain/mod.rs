@@ -1,7 +1,11 @@
+mod issue_template;
 mod new_subscriber;
+mod send_at;
 mod subscriber_email;
 mod subscriber_name;
 
+pub use issue_template::{IssueTemplate, MergeFields, MergeTag};
 pub use new_subscriber::NewSubscriber;
+pub use send_at::SendAt;
 pub use subscriber_email::SubscriberEmail;
 pub use subscriber_name::SubscriberName;
@@ -0,0 +1,102 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+/// When a scheduled newsletter issue should go out.
+#[derive(Debug, Clone, Copy)]
+pub struct SendAt(DateTime<Utc>);
+
+impl SendAt {
+    /// Accept RFC 3339 timestamps as well as the timezone-less values
+    /// submitted by `datetime-local` inputs, which are taken as UTC.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        if let Ok(timestamp) = DateTime::parse_from_rfc3339(s) {
+            return Ok(Self(timestamp.with_timezone(&Utc)));
+        }
+        ["%Y-%m-%dT%H:%M", "%Y-%m-%dT%H:%M:%S"]
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+            .map(|timestamp| Self(DateTime::from_utc(timestamp, Utc)))
+            .ok_or_else(|| format!("{} is not a valid date and time.", s))
+    }
+
+    /// Parse the optional send-at field of the admin forms: an empty value,
+    /// or a time in the past, means "right away".
+    pub fn parse_optional(s: Option<&str>) -> Result<Option<Self>, String> {
+        match s.map(str::trim).filter(|s| !s.is_empty()) {
+            None => Ok(None),
+            Some(s) => Ok(Some(Self::parse(s)?).filter(Self::is_in_the_future)),
+        }
+    }
+
+    /// Parse a new time for an issue that is already scheduled: it must be
+    /// in the future, or rescheduling would publish the issue by accident.
+    pub fn parse_future(s: &str) -> Result<Self, String> {
+        let send_at = Self::parse(s)?;
+        if send_at.is_in_the_future() {
+            Ok(send_at)
+        } else {
+            Err(format!("{} is in the past.", send_at))
+        }
+    }
+
+    pub fn is_in_the_future(&self) -> bool {
+        self.0 > Utc::now()
+    }
+}
+
+impl AsRef<DateTime<Utc>> for SendAt {
+    fn as_ref(&self) -> &DateTime<Utc> {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for SendAt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.format("%Y-%m-%d %H:%M UTC").fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SendAt;
+    use claims::{assert_err, assert_none, assert_ok};
+
+    #[test]
+    fn an_rfc3339_timestamp_is_accepted() {
+        let send_at = assert_ok!(SendAt::parse("2023-04-21T10:30:00+02:00"));
+        assert_eq!(send_at.as_ref().to_rfc3339(), "2023-04-21T08:30:00+00:00");
+    }
+
+    #[test]
+    fn a_datetime_local_value_is_taken_as_utc() {
+        let send_at = assert_ok!(SendAt::parse("2023-04-21T10:30"));
+        assert_eq!(send_at.as_ref().to_rfc3339(), "2023-04-21T10:30:00+00:00");
+    }
+
+    #[test]
+    fn an_empty_optional_value_means_right_away() {
+        assert_none!(assert_ok!(SendAt::parse_optional(Some("  "))));
+        assert_none!(assert_ok!(SendAt::parse_optional(None)));
+    }
+
+    #[test]
+    fn a_time_in_the_past_means_right_away() {
+        assert_none!(assert_ok!(SendAt::parse_optional(Some("2001-01-01T00:00"))));
+    }
+
+    #[test]
+    fn a_time_in_the_past_is_not_a_new_send_time() {
+        assert_err!(SendAt::parse_future("2001-01-01T00:00"));
+        assert_ok!(SendAt::parse_future("2099-01-01T00:00"));
+    }
+
+    #[test]
+    fn garbage_is_rejected() {
+        assert_err!(SendAt::parse("tomorrow"));
+    }
+
+    #[test]
+    fn an_empty_string_is_rejected() {
+        assert_err!(SendAt::parse(""));
+    }
+}
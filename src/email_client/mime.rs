@@ -0,0 +1,58 @@
+use anyhow::Context;
+use lettre::address::Envelope;
+use lettre::message::header::{HeaderName, HeaderValue, Headers};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::Message;
+use uuid::Uuid;
+
+use super::Email;
+use crate::domain::SubscriberEmail;
+
+/// A MIME message ready to be handed to an SMTP server or written to disk.
+pub(super) struct MimeMessage {
+    pub message_id: String,
+    pub envelope: Envelope,
+    pub formatted: Vec<u8>,
+}
+
+pub(super) fn build_message(
+    sender: &SubscriberEmail,
+    email: &Email,
+) -> Result<MimeMessage, anyhow::Error> {
+    let from: Mailbox = sender.as_ref().parse().context("Invalid sender address.")?;
+    let to: Mailbox = email
+        .recipient
+        .as_ref()
+        .parse()
+        .context("Invalid recipient address.")?;
+    let domain = sender.as_ref().rsplit('@').next().unwrap_or("localhost");
+    let message_id = format!("<{}@{}>", Uuid::new_v4(), domain);
+
+    let message = Message::builder()
+        .from(from)
+        .to(to)
+        .subject(&email.subject)
+        .message_id(Some(message_id.clone()))
+        .multipart(MultiPart::alternative_plain_html(
+            email.text_content.clone(),
+            email.html_content.clone(),
+        ))
+        .context("Failed to build the MIME message.")?;
+
+    // lettre only knows about typed headers: we prepend ours to the
+    // rendered message, header order does not matter.
+    let mut extra_headers = Headers::new();
+    for header in &email.headers {
+        let name = HeaderName::new_from_ascii(header.name.clone())
+            .map_err(|_| anyhow::anyhow!("Invalid header name: {}", header.name))?;
+        extra_headers.insert_raw(HeaderValue::new(name, header.value.clone()));
+    }
+    let mut formatted = extra_headers.to_string().into_bytes();
+    formatted.extend(message.formatted());
+
+    Ok(MimeMessage {
+        message_id,
+        envelope: message.envelope().clone(),
+        formatted,
+    })
+}
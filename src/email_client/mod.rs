@@ -0,0 +1,124 @@
+mod mime;
+mod outbox;
+mod postmark;
+mod smtp;
+
+use std::sync::Arc;
+
+pub use outbox::OutboxTransport;
+pub use postmark::{PostmarkTransport, RejectedEmail, MAX_BATCH_SIZE};
+pub use smtp::SmtpTransport;
+
+use crate::domain::SubscriberEmail;
+
+/// A way of getting emails to their recipients.
+#[async_trait::async_trait]
+pub trait EmailTransport: Send + Sync {
+    /// Deliver a single email.
+    /// Returns the id the transport assigned to the message, if any.
+    async fn send(
+        &self,
+        sender: &SubscriberEmail,
+        email: &Email,
+    ) -> Result<Option<String>, anyhow::Error>;
+
+    /// Deliver many emails at once.
+    /// The outer error means nothing could be sent; otherwise the outcome
+    /// of each email is returned, in the same order as `emails`.
+    ///
+    /// Transports without a bulk API send the emails one by one.
+    async fn send_batch(
+        &self,
+        sender: &SubscriberEmail,
+        emails: &[Email],
+    ) -> Result<Vec<Result<Option<String>, anyhow::Error>>, anyhow::Error> {
+        let mut outcomes = Vec::with_capacity(emails.len());
+        for email in emails {
+            outcomes.push(self.send(sender, email).await);
+        }
+        Ok(outcomes)
+    }
+}
+
+/// Sends emails on behalf of the newsletter, whatever the transport.
+#[derive(Clone)]
+pub struct EmailClient {
+    sender: SubscriberEmail,
+    transport: Arc<dyn EmailTransport>,
+}
+
+impl EmailClient {
+    pub fn new(sender: SubscriberEmail, transport: impl EmailTransport + 'static) -> Self {
+        Self {
+            sender,
+            transport: Arc::new(transport),
+        }
+    }
+
+    pub async fn send_email(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+    ) -> Result<(), anyhow::Error> {
+        self.send_email_with_headers(recipient, subject, html_content, text_content, &[])
+            .await
+            .map(|_| ())
+    }
+
+    /// Same as `send_email`, attaching extra headers (e.g. `List-Unsubscribe`)
+    /// to the outgoing message.
+    /// Returns the id the transport assigned to the message, if any.
+    pub async fn send_email_with_headers(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[EmailHeader],
+    ) -> Result<Option<String>, anyhow::Error> {
+        let email = Email {
+            recipient: recipient.clone(),
+            subject: subject.into(),
+            html_content: html_content.into(),
+            text_content: text_content.into(),
+            headers: headers.to_vec(),
+        };
+        self.transport.send(&self.sender, &email).await
+    }
+
+    /// Send many emails with as few round trips as the transport allows.
+    /// See [`EmailTransport::send_batch`].
+    pub async fn send_batch(
+        &self,
+        emails: &[Email],
+    ) -> Result<Vec<Result<Option<String>, anyhow::Error>>, anyhow::Error> {
+        self.transport.send_batch(&self.sender, emails).await
+    }
+}
+
+/// An email to be delivered, see [`EmailClient::send_batch`].
+pub struct Email {
+    pub recipient: SubscriberEmail,
+    pub subject: String,
+    pub html_content: String,
+    pub text_content: String,
+    pub headers: Vec<EmailHeader>,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct EmailHeader {
+    pub name: String,
+    pub value: String,
+}
+
+impl EmailHeader {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::path::PathBuf;
+
+use lettre::{AsyncFileTransport, AsyncTransport, Tokio1Executor};
+
+use super::mime::build_message;
+use super::{Email, EmailTransport};
+use crate::domain::SubscriberEmail;
+
+/// Write every email as an `.eml` file in a directory instead of sending
+/// it, to inspect what would go out during local development.
+pub struct OutboxTransport {
+    directory: PathBuf,
+    writer: AsyncFileTransport<Tokio1Executor>,
+}
+
+impl OutboxTransport {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        let directory = directory.into();
+        Self {
+            writer: AsyncFileTransport::new(&directory),
+            directory,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for OutboxTransport {
+    /// Returns the `Message-ID` of the email.
+    async fn send(
+        &self,
+        sender: &SubscriberEmail,
+        email: &Email,
+    ) -> Result<Option<String>, anyhow::Error> {
+        let message = build_message(sender, email)?;
+        tokio::fs::create_dir_all(&self.directory).await?;
+        self.writer
+            .send_raw(&message.envelope, &message.formatted)
+            .await?;
+        Ok(Some(message.message_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OutboxTransport;
+    use crate::domain::SubscriberEmail;
+    use crate::email_client::EmailClient;
+    use claims::assert_ok;
+
+    #[tokio::test]
+    async fn send_email_writes_an_eml_file_to_the_outbox() {
+        let directory = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let email_client = EmailClient::new(
+            SubscriberEmail::parse("newsletter@example.com".into()).unwrap(),
+            OutboxTransport::new(&directory),
+        );
+        let recipient = SubscriberEmail::parse("ursula@example.com".into()).unwrap();
+
+        let outcome = email_client
+            .send_email(&recipient, "An issue", "<p>Some HTML</p>", "Some text")
+            .await;
+
+        assert_ok!(outcome);
+        let files: Vec<_> = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].extension().unwrap(), "eml");
+        let content = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(content.contains("To: ursula@example.com"));
+        assert!(content.contains("Subject: An issue"));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
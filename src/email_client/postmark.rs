@@ -0,0 +1,559 @@
+use anyhow::Context;
+use reqwest::Client;
+use secrecy::ExposeSecret;
+
+use super::{Email, EmailHeader, EmailTransport};
+use crate::domain::SubscriberEmail;
+
+/// Postmark does not accept more than 500 messages per batch request.
+pub const MAX_BATCH_SIZE: usize = 500;
+
+/// Deliver emails through Postmark's HTTP API.
+pub struct PostmarkTransport {
+    http_client: Client,
+    base_url: String,
+    authorization_token: secrecy::Secret<String>,
+}
+
+impl PostmarkTransport {
+    pub fn new(
+        base_url: String,
+        authorization_token: secrecy::Secret<String>,
+        timeout: std::time::Duration,
+    ) -> Self {
+        let http_client = Client::builder().timeout(timeout).build().unwrap();
+
+        Self {
+            http_client,
+            base_url,
+            authorization_token,
+        }
+    }
+
+    /// Send up to `MAX_BATCH_SIZE` emails in a single request.
+    async fn send_chunk(
+        &self,
+        sender: &SubscriberEmail,
+        chunk: &[Email],
+    ) -> Result<Vec<Result<Option<String>, anyhow::Error>>, anyhow::Error> {
+        let url = format!("{}/email/batch", self.base_url);
+        let request_body: Vec<_> = chunk
+            .iter()
+            .map(|email| SendEmailRequest::new(sender, email))
+            .collect();
+
+        let response: Vec<BatchEmailResponse> = self
+            .http_client
+            .post(&url)
+            .header(
+                "X-Postmark-Server-Token",
+                self.authorization_token.expose_secret(),
+            )
+            .json(&request_body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .context("Failed to parse the response of the batch endpoint.")?;
+        if response.len() != chunk.len() {
+            anyhow::bail!(
+                "Sent {} emails in a batch, but got {} results back.",
+                chunk.len(),
+                response.len()
+            );
+        }
+        Ok(response
+            .into_iter()
+            .map(|r| match r.error_code {
+                0 => Ok(r.message_id),
+                error_code => Err(RejectedEmail {
+                    error_code,
+                    message: r.message,
+                }
+                .into()),
+            })
+            .collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for PostmarkTransport {
+    /// Returns the id Postmark assigned to the message, if it reported one.
+    async fn send(
+        &self,
+        sender: &SubscriberEmail,
+        email: &Email,
+    ) -> Result<Option<String>, anyhow::Error> {
+        let url = format!("{}/email", self.base_url);
+
+        let request_body = SendEmailRequest::new(sender, email);
+
+        let response = self
+            .http_client
+            .post(&url)
+            .header(
+                "X-Postmark-Server-Token",
+                self.authorization_token.expose_secret(),
+            )
+            .json(&request_body)
+            .send()
+            .await?
+            .error_for_status()?;
+        let message_id = response
+            .json::<SendEmailResponse>()
+            .await
+            .ok()
+            .map(|r| r.message_id);
+        Ok(message_id)
+    }
+
+    /// Use Postmark's batch endpoint: one request per `MAX_BATCH_SIZE` emails.
+    /// A request that fails only fails the emails of its own chunk.
+    async fn send_batch(
+        &self,
+        sender: &SubscriberEmail,
+        emails: &[Email],
+    ) -> Result<Vec<Result<Option<String>, anyhow::Error>>, anyhow::Error> {
+        let chunks = emails.chunks(MAX_BATCH_SIZE);
+        let n_chunks = chunks.len();
+        let mut n_failed_chunks = 0;
+        let mut outcomes = Vec::with_capacity(emails.len());
+        for chunk in chunks {
+            match self.send_chunk(sender, chunk).await {
+                Ok(chunk_outcomes) => outcomes.extend(chunk_outcomes),
+                // Every chunk failed: nothing was sent.
+                Err(e) if n_failed_chunks + 1 == n_chunks => return Err(e),
+                Err(e) => {
+                    n_failed_chunks += 1;
+                    outcomes.extend(
+                        chunk
+                            .iter()
+                            .map(|_| Err(anyhow::anyhow!("Failed to send the batch: {}", e))),
+                    );
+                }
+            }
+        }
+        Ok(outcomes)
+    }
+}
+
+/// A message of a batch that Postmark refused to deliver.
+#[derive(thiserror::Error, Debug)]
+#[error("The email was rejected by the provider (error code {error_code}): {message}")]
+pub struct RejectedEmail {
+    pub error_code: i64,
+    pub message: String,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    html_body: &'a str,
+    text_body: &'a str,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    headers: &'a [EmailHeader],
+}
+
+impl<'a> SendEmailRequest<'a> {
+    fn new(sender: &'a SubscriberEmail, email: &'a Email) -> Self {
+        Self {
+            from: sender.as_ref(),
+            to: email.recipient.as_ref(),
+            subject: &email.subject,
+            html_body: &email.html_content,
+            text_body: &email.text_content,
+            headers: &email.headers,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct SendEmailResponse {
+    #[serde(rename = "MessageID")]
+    message_id: String,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct BatchEmailResponse {
+    error_code: i64,
+    #[serde(default)]
+    message: String,
+    #[serde(rename = "MessageID", default)]
+    message_id: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PostmarkTransport, RejectedEmail, MAX_BATCH_SIZE};
+    use crate::domain::SubscriberEmail;
+    use crate::email_client::{Email, EmailClient, EmailHeader};
+    use claims::{assert_err, assert_ok, assert_ok_eq};
+    use fake::faker::internet::en::SafeEmail;
+    use fake::faker::lorem::en::{Paragraph, Sentence};
+    use fake::{Fake, Faker};
+    use wiremock::matchers::{any, header, header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// Generate a random email subject
+    fn subject() -> String {
+        Sentence(1..2).fake()
+    }
+
+    /// Generate a random email content
+    fn content() -> String {
+        Paragraph(1..10).fake()
+    }
+
+    /// Generate a random subscriber email
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).unwrap()
+    }
+
+    /// Get a test instance of `EmailClient`.
+    fn email_client(base_url: String) -> EmailClient {
+        EmailClient::new(
+            email(),
+            PostmarkTransport::new(
+                base_url,
+                secrecy::Secret::new(Faker.fake()),
+                std::time::Duration::from_millis(200),
+            ),
+        )
+    }
+
+    struct SendEmailBodyMatcher;
+
+    impl wiremock::Match for SendEmailBodyMatcher {
+        fn matches(&self, request: &wiremock::Request) -> bool {
+            let result: Result<serde_json::Value, _> = serde_json::from_slice(&request.body);
+            if let Ok(body) = result {
+                body.get("From").is_some()
+                    && body.get("To").is_some()
+                    && body.get("Subject").is_some()
+                    && body.get("HtmlBody").is_some()
+                    && body.get("TextBody").is_some()
+            } else {
+                false
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn send_email_sends_the_expected_request() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(header_exists("X-Postmark-Server-Token"))
+            .and(header("Content-Type", "application/json"))
+            .and(path("/email"))
+            .and(method("POST"))
+            .and(SendEmailBodyMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let _ = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+    }
+
+    struct HeadersBodyMatcher;
+
+    impl wiremock::Match for HeadersBodyMatcher {
+        fn matches(&self, request: &wiremock::Request) -> bool {
+            let result: Result<serde_json::Value, _> = serde_json::from_slice(&request.body);
+            if let Ok(body) = result {
+                body["Headers"]
+                    == serde_json::json!([
+                        {"Name": "List-Unsubscribe", "Value": "<https://example.com/u>"},
+                        {"Name": "List-Unsubscribe-Post", "Value": "List-Unsubscribe=One-Click"},
+                    ])
+            } else {
+                false
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn send_email_with_headers_forwards_the_headers() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(SendEmailBodyMatcher)
+            .and(HeadersBodyMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let headers = [
+            EmailHeader::new("List-Unsubscribe", "<https://example.com/u>"),
+            EmailHeader::new("List-Unsubscribe-Post", "List-Unsubscribe=One-Click"),
+        ];
+        let outcome = email_client
+            .send_email_with_headers(&email(), &subject(), &content(), &content(), &headers)
+            .await;
+
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_succeeds_if_the_server_returns_200() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        // We do not copy in all the matchers we have in the other test.
+        // The purpose of this test is not to assert on the request we
+        // are sending out!
+        // We add the bare minimum needed to trigger the path we want
+        // to test in `send_email`.
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_with_headers_returns_the_provider_message_id() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "To": "receiver@example.com",
+                "SubmittedAt": "2023-04-19T14:30:10.1234567-04:00",
+                "MessageID": "b7bc2f4a-e38e-4336-af7d-e6c392c2f817",
+                "ErrorCode": 0,
+                "Message": "OK"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email_with_headers(&email(), &subject(), &content(), &content(), &[])
+            .await;
+
+        assert_ok_eq!(
+            outcome,
+            Some("b7bc2f4a-e38e-4336-af7d-e6c392c2f817".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        // We do not copy in all the matchers we have in the other test.
+        // The purpose of this test is not to assert on the request we
+        // are sending out!
+        // We add the bare minimum needed to trigger the path we want
+        // to test in `send_email`.
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_times_out_if_the_server_takes_too_long() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        // We do not copy in all the matchers we have in the other test.
+        // The purpose of this test is not to assert on the request we
+        // are sending out!
+        // We add the bare minimum needed to trigger the path we want
+        // to test in `send_email`.
+        let response = ResponseTemplate::new(200).set_delay(std::time::Duration::from_secs(180));
+
+        Mock::given(any())
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        assert_err!(outcome);
+    }
+
+    fn batch_email() -> Email {
+        Email {
+            recipient: email(),
+            subject: subject(),
+            html_content: content(),
+            text_content: content(),
+            headers: vec![],
+        }
+    }
+
+    struct BatchBodyMatcher(usize);
+
+    impl wiremock::Match for BatchBodyMatcher {
+        fn matches(&self, request: &wiremock::Request) -> bool {
+            let result: Result<Vec<serde_json::Value>, _> = serde_json::from_slice(&request.body);
+            if let Ok(body) = result {
+                body.len() == self.0
+                    && body.iter().all(|email| {
+                        email.get("From").is_some()
+                            && email.get("To").is_some()
+                            && email.get("Subject").is_some()
+                            && email.get("HtmlBody").is_some()
+                            && email.get("TextBody").is_some()
+                    })
+            } else {
+                false
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn send_batch_sends_all_emails_in_one_request() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(header_exists("X-Postmark-Server-Token"))
+            .and(path("/email/batch"))
+            .and(method("POST"))
+            .and(BatchBodyMatcher(2))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {"ErrorCode": 0, "Message": "OK", "MessageID": "first-id"},
+                {"ErrorCode": 0, "Message": "OK", "MessageID": "second-id"},
+            ])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_batch(&[batch_email(), batch_email()])
+            .await
+            .unwrap();
+
+        assert_ok_eq!(&outcome[0], &Some("first-id".to_string()));
+        assert_ok_eq!(&outcome[1], &Some("second-id".to_string()));
+    }
+
+    #[tokio::test]
+    async fn send_batch_reports_the_outcome_of_every_email() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {"ErrorCode": 0, "Message": "OK", "MessageID": "first-id"},
+                {"ErrorCode": 406, "Message": "Inactive recipient"},
+            ])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_batch(&[batch_email(), batch_email()])
+            .await
+            .unwrap();
+
+        assert_ok!(&outcome[0]);
+        let rejected = outcome[1].as_ref().unwrap_err();
+        assert_eq!(
+            rejected.downcast_ref::<RejectedEmail>().unwrap().error_code,
+            406
+        );
+    }
+
+    #[tokio::test]
+    async fn send_batch_splits_large_batches() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let results: Vec<_> = (0..MAX_BATCH_SIZE)
+            .map(|_| serde_json::json!({"ErrorCode": 0, "Message": "OK"}))
+            .collect();
+
+        Mock::given(BatchBodyMatcher(MAX_BATCH_SIZE))
+            .respond_with(ResponseTemplate::new(200).set_body_json(results))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(BatchBodyMatcher(1))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!([{"ErrorCode": 0, "Message": "OK"}])),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let emails: Vec<_> = (0..=MAX_BATCH_SIZE).map(|_| batch_email()).collect();
+        let outcome = email_client.send_batch(&emails).await.unwrap();
+
+        assert_eq!(outcome.len(), MAX_BATCH_SIZE + 1);
+    }
+
+    #[tokio::test]
+    async fn send_batch_keeps_the_outcomes_of_the_chunks_that_went_through() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let results: Vec<_> = (0..MAX_BATCH_SIZE)
+            .map(|_| serde_json::json!({"ErrorCode": 0, "Message": "OK"}))
+            .collect();
+
+        Mock::given(BatchBodyMatcher(MAX_BATCH_SIZE))
+            .respond_with(ResponseTemplate::new(200).set_body_json(results))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(BatchBodyMatcher(1))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let emails: Vec<_> = (0..=MAX_BATCH_SIZE).map(|_| batch_email()).collect();
+        let outcome = email_client.send_batch(&emails).await.unwrap();
+
+        assert_eq!(outcome.len(), MAX_BATCH_SIZE + 1);
+        assert!(outcome[..MAX_BATCH_SIZE].iter().all(Result::is_ok));
+        assert_err!(&outcome[MAX_BATCH_SIZE]);
+    }
+
+    #[tokio::test]
+    async fn send_batch_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_batch(&[batch_email()]).await;
+
+        assert_err!(outcome);
+    }
+}
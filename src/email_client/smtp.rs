@@ -0,0 +1,167 @@
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use secrecy::ExposeSecret;
+
+use super::mime::build_message;
+use super::{Email, EmailTransport};
+use crate::configuration::SmtpSettings;
+use crate::domain::SubscriberEmail;
+
+/// Deliver emails to an SMTP server.
+pub struct SmtpTransport {
+    mailer: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpTransport {
+    pub fn new(
+        settings: &SmtpSettings,
+        timeout: std::time::Duration,
+    ) -> Result<Self, lettre::transport::smtp::Error> {
+        let mut builder = if settings.require_tls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host)?
+        } else {
+            // Local SMTP sinks (e.g. MailHog) do not speak TLS.
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host)
+        };
+        builder = builder.port(settings.port).timeout(Some(timeout));
+        if let (Some(username), Some(password)) = (&settings.username, &settings.password) {
+            builder = builder.credentials(Credentials::new(
+                username.clone(),
+                password.expose_secret().clone(),
+            ));
+        }
+        Ok(Self {
+            mailer: builder.build(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for SmtpTransport {
+    /// Returns the `Message-ID` of the email.
+    async fn send(
+        &self,
+        sender: &SubscriberEmail,
+        email: &Email,
+    ) -> Result<Option<String>, anyhow::Error> {
+        let message = build_message(sender, email)?;
+        self.mailer
+            .send_raw(&message.envelope, &message.formatted)
+            .await?;
+        Ok(Some(message.message_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SmtpTransport;
+    use crate::configuration::SmtpSettings;
+    use crate::domain::SubscriberEmail;
+    use crate::email_client::{EmailClient, EmailHeader};
+    use claims::{assert_err, assert_ok};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// A bare-bones SMTP server accepting a single email.
+    /// Resolves to the data of the email it received.
+    async fn smtp_sink() -> (u16, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            writer.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+
+            let mut data = String::new();
+            let mut receiving_data = false;
+            while let Some(line) = lines.next_line().await.unwrap() {
+                if receiving_data {
+                    if line == "." {
+                        receiving_data = false;
+                        writer.write_all(b"250 OK\r\n").await.unwrap();
+                    } else {
+                        data.push_str(&line);
+                        data.push('\n');
+                    }
+                    continue;
+                }
+                let command = line.split_whitespace().next().unwrap_or("").to_uppercase();
+                let reply: &[u8] = match command.as_str() {
+                    "DATA" => {
+                        receiving_data = true;
+                        b"354 End data with <CR><LF>.<CR><LF>\r\n"
+                    }
+                    "QUIT" => {
+                        writer.write_all(b"221 Bye\r\n").await.unwrap();
+                        break;
+                    }
+                    _ => b"250 OK\r\n",
+                };
+                writer.write_all(reply).await.unwrap();
+            }
+            data
+        });
+        (port, handle)
+    }
+
+    fn email_client(port: u16) -> EmailClient {
+        let settings = SmtpSettings {
+            host: "127.0.0.1".into(),
+            port,
+            username: None,
+            password: None,
+            require_tls: false,
+        };
+        let transport =
+            SmtpTransport::new(&settings, std::time::Duration::from_millis(500)).unwrap();
+        EmailClient::new(
+            SubscriberEmail::parse("newsletter@example.com".into()).unwrap(),
+            transport,
+        )
+    }
+
+    #[tokio::test]
+    async fn send_email_delivers_the_message_to_the_smtp_server() {
+        let (port, sink) = smtp_sink().await;
+        let recipient = SubscriberEmail::parse("ursula@example.com".into()).unwrap();
+        let headers = [EmailHeader::new(
+            "List-Unsubscribe",
+            "<https://example.com/u>",
+        )];
+
+        let outcome = email_client(port)
+            .send_email_with_headers(
+                &recipient,
+                "An issue",
+                "<p>Some HTML</p>",
+                "Some text",
+                &headers,
+            )
+            .await;
+
+        let message_id = assert_ok!(outcome).unwrap();
+        let data = sink.await.unwrap();
+        assert!(data.contains("To: ursula@example.com"));
+        assert!(data.contains("Subject: An issue"));
+        assert!(data.contains("List-Unsubscribe: <https://example.com/u>"));
+        assert!(data.contains(&format!("Message-ID: {}", message_id)));
+        assert!(data.contains("Some text"));
+        assert!(data.contains("<p>Some HTML</p>"));
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_smtp_server_is_unreachable() {
+        let port = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        let recipient = SubscriberEmail::parse("ursula@example.com".into()).unwrap();
+
+        let outcome = email_client(port)
+            .send_email(&recipient, "An issue", "<p>Some HTML</p>", "Some text")
+            .await;
+
+        assert_err!(outcome);
+    }
+}
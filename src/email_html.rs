@@ -0,0 +1,202 @@
+use std::borrow::Cow;
+
+use css_inline::CSSInliner;
+use html5ever::{
+    local_name, namespace_url, ns, parse_document, parse_fragment, tendril::TendrilSink,
+    tree_builder::TreeBuilderOpts, ParseOpts, QualName,
+};
+use markup5ever_rcdom::RcDom;
+
+/// Get the HTML of an issue ready to be sent: reject markup that does not
+/// parse cleanly, move `<style>` rules into `style` attributes (many email
+/// clients ignore style sheets) and strip anything unsafe (scripts, event
+/// handler attributes, `javascript:` links, ...).
+pub fn prepare(html: &str) -> Result<String, String> {
+    check_syntax(html)?;
+    let inlined = CSSInliner::options()
+        .load_remote_stylesheets(false)
+        .build()
+        .inline(html)
+        .map_err(|e| format!("The CSS of the HTML content is invalid: {}.", e))?;
+    Ok(sanitize(&inlined))
+}
+
+/// Strip anything unsafe, keeping the markup and inline styles emails rely on.
+pub fn sanitize(html: &str) -> String {
+    sanitizer().clean(html).to_string()
+}
+
+/// Replace the target of every `http(s)` link for which `rewrite` returns
+/// a new one. `html` is expected to be sanitised already.
+pub fn rewrite_links(
+    html: &str,
+    rewrite: impl Fn(&str) -> Option<String> + Send + Sync + 'static,
+) -> String {
+    let mut builder = sanitizer();
+    builder.attribute_filter(move |element, attribute, value| {
+        let is_web_link = value.starts_with("http://") || value.starts_with("https://");
+        match (element, attribute) {
+            ("a", "href") if is_web_link => Some(rewrite(value).map_or(value.into(), Cow::Owned)),
+            _ => Some(value.into()),
+        }
+    });
+    builder.clean(html).to_string()
+}
+
+/// The parse errors that reveal broken markup: elements left open, end tags
+/// without a matching start tag, a document cut short. Everything else the
+/// parser reports (legacy doctypes, quirks mode, sloppy character references,
+/// ...) is rendered fine by browsers and email clients alike.
+const STRUCTURAL_ERRORS: &[&str] = &[
+    "Unexpected token",
+    "Unexpected open tag at end of body",
+    "Unexpected open element",
+    "Found special tag while closing generic tag",
+    "No matching tag to close",
+    "No <p> tag to close",
+    "No heading tag to close",
+    "Closing wrong heading tag",
+    "Formatting element not open",
+    "Formatting element not in scope",
+    "Formatting element not current node",
+    "Unexpected EOF",
+];
+
+fn check_syntax(html: &str) -> Result<(), String> {
+    let options = ParseOpts {
+        tree_builder: TreeBuilderOpts {
+            // Do not report a missing doctype, which only switches the
+            // parser to quirks mode, as an "Unexpected token".
+            iframe_srcdoc: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    // Whole documents are parsed as such, so that their doctype and
+    // `<html>` element are not reported as misplaced.
+    let trimmed = html.trim_start().to_lowercase();
+    let dom = if trimmed.starts_with("<!doctype") || trimmed.starts_with("<html") {
+        parse_document(RcDom::default(), options).one(html)
+    } else {
+        parse_fragment(
+            RcDom::default(),
+            options,
+            QualName::new(None, ns!(html), local_name!("body")),
+            vec![],
+        )
+        .one(html)
+    };
+    let mut errors: Vec<&str> = Vec::new();
+    for error in &dom.errors {
+        if STRUCTURAL_ERRORS.contains(&error.as_ref()) && !errors.contains(&error.as_ref()) {
+            errors.push(error);
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "The HTML content could not be parsed: {}.",
+            errors.join(", ")
+        ))
+    }
+}
+
+fn sanitizer() -> ammonia::Builder<'static> {
+    let mut builder = ammonia::Builder::default();
+    builder
+        .add_generic_attributes(["style", "align", "valign", "width", "height", "bgcolor"])
+        .add_tag_attributes("table", ["border", "cellpadding", "cellspacing"]);
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{prepare, rewrite_links};
+    use claims::{assert_err, assert_ok};
+
+    #[test]
+    fn style_rules_are_inlined() {
+        let html = assert_ok!(prepare(
+            "<style>p { color: red; }</style><p>Hello</p><p style=\"margin: 0\">World</p>"
+        ));
+        assert!(!html.contains("<style"));
+        assert!(html.contains(r#"<p style="color: red;">Hello</p>"#));
+        assert!(html.contains("margin: 0"));
+    }
+
+    #[test]
+    fn scripts_and_unsafe_attributes_are_stripped() {
+        let html = assert_ok!(prepare(
+            r#"<p onclick="steal()">Hi</p><script>steal()</script><a href="javascript:steal()">x</a>"#
+        ));
+        assert!(!html.contains("steal"));
+        assert!(html.contains("<p>Hi</p>"));
+    }
+
+    #[test]
+    fn whole_documents_are_accepted() {
+        let html = assert_ok!(prepare(
+            "<!DOCTYPE html><html><head><style>h1 { font-size: 20px; }</style></head>\
+            <body><h1>Title</h1></body></html>"
+        ));
+        assert!(html.contains("<h1 style=\"font-size: 20px;\">Title</h1>"));
+    }
+
+    #[test]
+    fn merge_tags_are_preserved() {
+        let html = assert_ok!(prepare(
+            r#"<p>Hi {{ name }}</p><a href="{{ unsubscribe_url }}">Unsubscribe</a>"#
+        ));
+        assert!(html.contains("Hi {{ name }}"));
+        assert!(html.contains(r#"href="{{ unsubscribe_url }}""#));
+    }
+
+    #[test]
+    fn xhtml_documents_are_accepted() {
+        let html = assert_ok!(prepare(
+            r#"<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Transitional//EN" "http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd">
+<html xmlns="http://www.w3.org/1999/xhtml">
+<head>
+<meta http-equiv="Content-Type" content="text/html; charset=UTF-8" />
+<title>Newsletter</title>
+</head>
+<body>
+<table width="100%" cellpadding="0" cellspacing="0" border="0">
+<tr><td align="center">Hi {{ name }},<br /><a href="{{ unsubscribe_url }}">Unsubscribe</a></td></tr>
+</table>
+</body>
+</html>"#
+        ));
+        assert!(html.contains("Hi {{ name }},<br>"));
+    }
+
+    #[test]
+    fn html_without_a_doctype_is_accepted() {
+        assert_ok!(prepare("<html><body><p>Hi &copy 2024</p></body></html>"));
+    }
+
+    #[test]
+    fn malformed_html_is_rejected() {
+        assert_err!(prepare("<p>Unclosed <b>bold"));
+        assert_err!(prepare("<p>Stray</div>"));
+        assert_err!(prepare("<b><p>Overlapping</b></p>"));
+        assert_err!(prepare("<p>Cut short<a href=\"x\""));
+    }
+
+    #[test]
+    fn web_links_are_rewritten() {
+        let html = rewrite_links(
+            r#"<p><a href="https://example.com/?a=1&amp;b=2">x</a> <a href="mailto:a@example.com">y</a> <a href="https://example.com/keep">z</a></p>"#,
+            |url| {
+                (!url.ends_with("/keep"))
+                    .then(|| format!("https://t.example.com/?to={}", urlencoding::encode(url)))
+            },
+        );
+        assert!(html.contains(
+            r#"href="https://t.example.com/?to=https%3A%2F%2Fexample.com%2F%3Fa%3D1%26b%3D2""#
+        ));
+        assert!(html.contains(r#"href="mailto:a@example.com""#));
+        assert!(html.contains(r#"href="https://example.com/keep""#));
+    }
+}
@@ -1,153 +1,626 @@
+use std::collections::{hash_map::Entry, HashMap};
 use std::time::Duration;
 
-use sqlx::{PgPool, Postgres, Transaction};
-use tracing::{field::display, Span};
+use chrono::Utc;
+use sqlx::{postgres::PgListener, PgPool, Postgres, Transaction};
+use tokio::task::JoinSet;
+use tracing::Span;
 use uuid::Uuid;
 
 use crate::{
-    configuration::Settings, domain::SubscriberEmail, email_client::EmailClient,
-    startup::get_connection_pool,
+    configuration::{IssueDeliverySettings, Settings},
+    domain::{IssueTemplate, MergeFields, MergeTag, SubscriberEmail},
+    email_client::{Email, EmailClient, EmailHeader},
+    email_html,
+    rate_limiter::RateLimiter,
+    routes::{click_link, issue_link, open_pixel_link, unsubscribe_link},
+    startup::{get_connection_pool, ApplicationBaseUrl, HmacSecret},
 };
 
+/// Postgres channel notified whenever new tasks land in the delivery queue.
+pub const ISSUE_DELIVERY_CHANNEL: &str = "issue_delivery_queue";
+
+/// Wake up idle workers: there are new tasks in the delivery queue.
+/// The notification is only sent once `transaction` commits, so workers
+/// never wake up before the tasks are visible to them.
+#[tracing::instrument(skip_all)]
+pub async fn notify_workers(
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT pg_notify($1, '')")
+        .bind(ISSUE_DELIVERY_CHANNEL)
+        .execute(transaction)
+        .await?;
+    Ok(())
+}
+
+/// Queue the delivery of an issue to every confirmed subscriber.
+/// Addresses that unsubscribed, bounced or complained are left out.
+#[tracing::instrument(skip(transaction))]
+pub async fn enqueue_delivery_tasks(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (
+            newsletter_issue_id,
+            subscriber_email
+        )
+        SELECT $1, email
+        FROM subscriptions
+        WHERE status = 'confirmed'
+        "#,
+        newsletter_issue_id,
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
+/// Publish the scheduled issues whose time has come, enqueueing their
+/// delivery to the subscribers confirmed at this point.
+/// Returns how many issues were published.
+#[tracing::instrument(skip_all)]
+pub async fn publish_due_issues(pool: &PgPool) -> Result<usize, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let issues = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            status = 'published',
+            published_at = now()
+        WHERE newsletter_issue_id IN (
+            SELECT newsletter_issue_id
+            FROM newsletter_issues
+            WHERE status = 'scheduled' AND scheduled_for <= now()
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING newsletter_issue_id
+        "#,
+    )
+    .fetch_all(&mut transaction)
+    .await?;
+    for issue in &issues {
+        enqueue_delivery_tasks(&mut transaction, issue.newsletter_issue_id).await?;
+    }
+    if !issues.is_empty() {
+        notify_workers(&mut transaction).await?;
+    }
+    transaction.commit().await?;
+    Ok(issues.len())
+}
+
 pub enum ExecutionOutcome {
     TaskCompleted,
     EmptyQueue,
 }
 
-#[
-tracing::instrument(
-    skip_all,
-    fields(
-        newsletter_issue_id=tracing::field::Empty,
-        subscriber_email=tracing::field::Empty
-    ),
-    err
-)]
+/// Dequeue up to `settings.batch_size` tasks and attempt their delivery.
+#[tracing::instrument(skip_all, fields(n_tasks = tracing::field::Empty), err)]
 pub async fn try_execute_task(
     pool: &PgPool,
     email_client: &EmailClient,
+    base_url: &ApplicationBaseUrl,
+    hmac_secret: &HmacSecret,
+    settings: &IssueDeliverySettings,
+    rate_limiter: &RateLimiter,
 ) -> Result<ExecutionOutcome, anyhow::Error> {
-    let task = dequeue_task(pool).await?;
-    if task.is_none() {
+    let Some((mut transaction, tasks)) = dequeue_tasks(pool, settings.batch_size.max(1)).await?
+    else {
         return Ok(ExecutionOutcome::EmptyQueue);
+    };
+    Span::current().record("n_tasks", tasks.len());
+
+    let mut issues = HashMap::new();
+    let mut deliveries = Vec::with_capacity(tasks.len());
+    for task in &tasks {
+        if let Entry::Vacant(entry) = issues.entry(task.newsletter_issue_id) {
+            entry.insert(get_issue(&mut transaction, task.newsletter_issue_id).await?);
+        }
+        let issue = &issues[&task.newsletter_issue_id];
+        if let Some(email) =
+            prepare_email(&mut transaction, base_url, hmac_secret, issue, task).await?
+        {
+            deliveries.push((task, email));
+        }
     }
-    let (transaction, issue_id, email) = task.unwrap();
-    Span::current()
-        .record("newsletter_issue_id", &display(issue_id))
-        .record("subscriber_email", &display(&email));
-
-    match SubscriberEmail::parse(email.clone()) {
-        Ok(email) => {
-            let issue = get_issue(pool, issue_id).await?;
-            if let Err(e) = email_client
-                .send_email(
-                    &email,
-                    &issue.title,
-                    &issue.html_content,
-                    &issue.text_content,
-                )
-                .await
-            {
-                tracing::error!(
-                    error.cause_chain = ?e,
-                    error.message = %e,
-                    "Failed to deliver issue to a confirmed subscriber. \
-                        Skipping.",
+
+    if !deliveries.is_empty() {
+        rate_limiter.acquire_many(deliveries.len()).await;
+        let (tasks, emails): (Vec<_>, Vec<_>) = deliveries.into_iter().unzip();
+        let outcomes = send_emails(email_client, &emails).await;
+        for (task, outcome) in tasks.into_iter().zip(outcomes) {
+            record_delivery_outcome(&mut transaction, settings, task, outcome).await?;
+        }
+    }
+    transaction.commit().await?;
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+/// Build the email for a task.
+/// Returns `None` if the task cannot be delivered: it is then logged as
+/// failed and removed from the queue.
+#[tracing::instrument(
+    skip_all,
+    fields(
+        newsletter_issue_id=%task.newsletter_issue_id,
+        subscriber_email=%task.subscriber_email
+    ),
+    err
+)]
+async fn prepare_email(
+    transaction: &mut PgTransaction,
+    base_url: &ApplicationBaseUrl,
+    hmac_secret: &HmacSecret,
+    issue: &NewsletterIssue,
+    task: &Task,
+) -> Result<Option<Email>, anyhow::Error> {
+    match (
+        SubscriberEmail::parse(task.subscriber_email.clone()),
+        task.subscriber_id,
+    ) {
+        (Ok(recipient), Some(subscriber_id)) => {
+            let unsubscribe_link = unsubscribe_link(&base_url.0, hmac_secret, subscriber_id);
+            let issue_link = issue_link(&base_url.0, task.newsletter_issue_id);
+            let fields = MergeFields {
+                name: task.subscriber_name.as_deref().unwrap_or_default(),
+                email: recipient.as_ref(),
+                unsubscribe_url: unsubscribe_link.as_str(),
+            };
+            // Templates placing the unsubscribe link themselves do not get
+            // the default footer.
+            let mut html_content = issue.html_content.render_html(&fields);
+            if issue.track_clicks {
+                html_content = track_clicks(
+                    &html_content,
+                    base_url,
+                    hmac_secret,
+                    task.newsletter_issue_id,
+                    subscriber_id,
+                    &unsubscribe_link,
                 );
             }
+            html_content.push_str(&format!(
+                "<p><a href=\"{}\">View in browser</a></p>",
+                issue_link
+            ));
+            if !issue.html_content.uses(MergeTag::UnsubscribeUrl) {
+                html_content.push_str(&format!(
+                    "<p><a href=\"{}\">Unsubscribe</a></p>",
+                    unsubscribe_link
+                ));
+            }
+            if issue.track_opens {
+                html_content.push_str(&format!(
+                    r#"<img src="{}" width="1" height="1" alt="">"#,
+                    open_pixel_link(
+                        &base_url.0,
+                        hmac_secret,
+                        task.newsletter_issue_id,
+                        subscriber_id
+                    )
+                ));
+            }
+            let mut text_content = issue.text_content.render_text(&fields);
+            text_content.push_str(&format!("\n\nView in browser: {}", issue_link));
+            if !issue.text_content.uses(MergeTag::UnsubscribeUrl) {
+                text_content.push_str(&format!("\n\nUnsubscribe: {}", unsubscribe_link));
+            }
+            // RFC 8058: lets mailbox providers offer a native one-click
+            // unsubscribe button, which POSTs to the same link.
+            let headers = vec![
+                EmailHeader::new("List-Unsubscribe", format!("<{}>", unsubscribe_link)),
+                EmailHeader::new("List-Unsubscribe-Post", "List-Unsubscribe=One-Click"),
+            ];
+            return Ok(Some(Email {
+                recipient,
+                subject: issue.title.clone(),
+                html_content,
+                text_content,
+                headers,
+            }));
         }
-        Err(e) => {
+        (Err(e), _) => {
             tracing::error!(
                 error.cause_chain = ?e,
                 error.message = %e,
                 "Skipping a confirmed subscriber. \
                     Their stored contact details are invalid",
             );
+            log_delivery_attempt(transaction, task, DeliveryOutcome::Failed, None, Some(&e))
+                .await?;
+        }
+        (Ok(_), None) => {
+            tracing::warn!("Skipping a subscriber who no longer exists.");
+            log_delivery_attempt(
+                transaction,
+                task,
+                DeliveryOutcome::Failed,
+                None,
+                Some("The subscriber no longer exists."),
+            )
+            .await?;
+        }
+    }
+    delete_task(transaction, task).await?;
+    Ok(None)
+}
+
+/// Route the links of an issue through the click tracking redirect.
+/// The unsubscribe link is left alone: it must keep working as is.
+fn track_clicks(
+    html_content: &str,
+    base_url: &ApplicationBaseUrl,
+    hmac_secret: &HmacSecret,
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+    unsubscribe_link: &str,
+) -> String {
+    let base_url = base_url.0.clone();
+    let hmac_secret = hmac_secret.clone();
+    let unsubscribe_link = unsubscribe_link.to_string();
+    email_html::rewrite_links(html_content, move |url| {
+        (url != unsubscribe_link).then(|| {
+            click_link(
+                &base_url,
+                &hmac_secret,
+                newsletter_issue_id,
+                subscriber_id,
+                url,
+            )
+        })
+    })
+}
+
+/// Deliver the emails, with a single request whenever possible.
+/// Returns the provider message id or the error for each email, in order.
+async fn send_emails(
+    email_client: &EmailClient,
+    emails: &[Email],
+) -> Vec<Result<Option<String>, anyhow::Error>> {
+    if let [email] = emails {
+        let outcome = email_client
+            .send_email_with_headers(
+                &email.recipient,
+                &email.subject,
+                &email.html_content,
+                &email.text_content,
+                &email.headers,
+            )
+            .await;
+        return vec![outcome];
+    }
+    match email_client.send_batch(emails).await {
+        Ok(outcomes) => outcomes,
+        // None of the emails went out: all of them are retried.
+        Err(e) => emails
+            .iter()
+            .map(|_| Err(anyhow::anyhow!("Failed to send the batch: {}", e)))
+            .collect(),
+    }
+}
+
+#[tracing::instrument(
+    skip_all,
+    fields(
+        newsletter_issue_id=%task.newsletter_issue_id,
+        subscriber_email=%task.subscriber_email
+    ),
+    err
+)]
+async fn record_delivery_outcome(
+    transaction: &mut PgTransaction,
+    settings: &IssueDeliverySettings,
+    task: &Task,
+    outcome: Result<Option<String>, anyhow::Error>,
+) -> Result<(), anyhow::Error> {
+    match outcome {
+        Ok(message_id) => {
+            log_delivery_attempt(
+                transaction,
+                task,
+                DeliveryOutcome::Sent,
+                message_id.as_deref(),
+                None,
+            )
+            .await?;
+            delete_task(transaction, task).await
+        }
+        Err(e) if task.n_retries < settings.max_retries => {
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                n_retries = task.n_retries,
+                "Failed to deliver issue to a confirmed subscriber. \
+                    Retrying later.",
+            );
+            let error = e.to_string();
+            log_delivery_attempt(
+                transaction,
+                task,
+                DeliveryOutcome::Retrying,
+                None,
+                Some(&error),
+            )
+            .await?;
+            let delay = settings.retry_delay(task.n_retries);
+            reschedule_task(transaction, task, delay).await
+        }
+        Err(e) => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                n_retries = task.n_retries,
+                "Failed to deliver issue to a confirmed subscriber. \
+                    Moving it to the dead-letter table.",
+            );
+            let error = e.to_string();
+            log_delivery_attempt(
+                transaction,
+                task,
+                DeliveryOutcome::Failed,
+                None,
+                Some(&error),
+            )
+            .await?;
+            dead_letter_task(transaction, task, &error).await
         }
     }
-    delete_task(transaction, issue_id, &email).await?;
-    Ok(ExecutionOutcome::TaskCompleted)
 }
 
 type PgTransaction = Transaction<'static, Postgres>;
 
-#[tracing::instrument(skip_all)]
-async fn dequeue_task(
+struct Task {
+    newsletter_issue_id: Uuid,
+    subscriber_email: String,
+    // `None` if the subscriber was removed after the task was enqueued.
+    subscriber_id: Option<Uuid>,
+    subscriber_name: Option<String>,
+    n_retries: i32,
+}
+
+/// Lock up to `batch_size` due tasks. Concurrent workers skip the rows we
+/// hold, so they never attempt the same delivery twice.
+#[tracing::instrument(skip(pool))]
+async fn dequeue_tasks(
     pool: &PgPool,
-) -> Result<Option<(PgTransaction, Uuid, String)>, anyhow::Error> {
+    batch_size: i64,
+) -> Result<Option<(PgTransaction, Vec<Task>)>, anyhow::Error> {
     let mut transaction = pool.begin().await?;
-    let r = sqlx::query!(
+    let tasks = sqlx::query_as!(
+        Task,
         r#"
         SELECT
-            newsletter_issue_id,
-            subscriber_email 
-        FROM issue_delivery_queue
-        FOR UPDATE
+            q.newsletter_issue_id,
+            q.subscriber_email,
+            s.id AS "subscriber_id?",
+            s.name AS "subscriber_name?",
+            q.n_retries
+        FROM issue_delivery_queue q
+        LEFT JOIN subscriptions s ON s.email = q.subscriber_email
+        WHERE q.execute_after <= now()
+        FOR UPDATE OF q
         SKIP LOCKED
-        LIMIT 1
+        LIMIT $1
         "#,
+        batch_size
     )
-    .fetch_optional(&mut transaction)
+    .fetch_all(&mut transaction)
     .await?;
 
-    if let Some(r) = r {
-        Ok(Some((
-            transaction,
-            r.newsletter_issue_id,
-            r.subscriber_email,
-        )))
-    } else {
-        Ok(None)
+    if tasks.is_empty() {
+        return Ok(None);
     }
+    Ok(Some((transaction, tasks)))
 }
 
 #[tracing::instrument(skip_all)]
-async fn delete_task(
-    mut transaction: PgTransaction,
-    issue_id: Uuid,
-    email: &str,
-) -> Result<(), anyhow::Error> {
+async fn delete_task(transaction: &mut PgTransaction, task: &Task) -> Result<(), anyhow::Error> {
     sqlx::query!(
         r#"
         DELETE FROM issue_delivery_queue
         WHERE
             newsletter_issue_id = $1 AND
             subscriber_email = $2 "#,
-        issue_id,
-        email
+        task.newsletter_issue_id,
+        task.subscriber_email
     )
-    .execute(&mut transaction)
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
+/// Put the task back in the queue, to be picked up again once `delay`
+/// has elapsed.
+#[tracing::instrument(skip_all)]
+async fn reschedule_task(
+    transaction: &mut PgTransaction,
+    task: &Task,
+    delay: Duration,
+) -> Result<(), anyhow::Error> {
+    let execute_after = Utc::now() + chrono::Duration::from_std(delay)?;
+    sqlx::query!(
+        r#"
+        UPDATE issue_delivery_queue
+        SET
+            n_retries = n_retries + 1,
+            execute_after = $3
+        WHERE
+            newsletter_issue_id = $1 AND
+            subscriber_email = $2 "#,
+        task.newsletter_issue_id,
+        task.subscriber_email,
+        execute_after
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
+/// Move a task that ran out of retries to the dead-letter table.
+#[tracing::instrument(skip_all)]
+async fn dead_letter_task(
+    transaction: &mut PgTransaction,
+    task: &Task,
+    error: &str,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_dead_letters (
+            newsletter_issue_id,
+            subscriber_email,
+            n_retries,
+            last_error,
+            failed_at
+        )
+        VALUES ($1, $2, $3, $4, now())
+        ON CONFLICT (newsletter_issue_id, subscriber_email) DO UPDATE
+        SET
+            n_retries = EXCLUDED.n_retries,
+            last_error = EXCLUDED.last_error,
+            failed_at = EXCLUDED.failed_at
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_email,
+        task.n_retries,
+        error
+    )
+    .execute(&mut *transaction)
+    .await?;
+    delete_task(transaction, task).await
+}
+
+enum DeliveryOutcome {
+    Sent,
+    Retrying,
+    Failed,
+}
+
+impl DeliveryOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryOutcome::Sent => "sent",
+            DeliveryOutcome::Retrying => "retrying",
+            DeliveryOutcome::Failed => "failed",
+        }
+    }
+}
+
+/// Record the outcome of the latest delivery attempt for a task.
+#[tracing::instrument(skip_all)]
+async fn log_delivery_attempt(
+    transaction: &mut PgTransaction,
+    task: &Task,
+    outcome: DeliveryOutcome,
+    provider_message_id: Option<&str>,
+    error: Option<&str>,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_log (
+            newsletter_issue_id,
+            subscriber_email,
+            outcome,
+            provider_message_id,
+            n_attempts,
+            last_error,
+            first_attempted_at,
+            last_attempted_at
+        )
+        VALUES ($1, $2, $3, $4, 1, $5, now(), now())
+        ON CONFLICT (newsletter_issue_id, subscriber_email) DO UPDATE
+        SET
+            outcome = EXCLUDED.outcome,
+            provider_message_id = EXCLUDED.provider_message_id,
+            n_attempts = issue_delivery_log.n_attempts + 1,
+            last_error = EXCLUDED.last_error,
+            last_attempted_at = EXCLUDED.last_attempted_at
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_email,
+        outcome.as_str(),
+        provider_message_id,
+        error
+    )
+    .execute(transaction)
     .await?;
-    transaction.commit().await?;
     Ok(())
 }
 
 struct NewsletterIssue {
     title: String,
-    text_content: String,
-    html_content: String,
+    text_content: IssueTemplate,
+    html_content: IssueTemplate,
+    track_opens: bool,
+    track_clicks: bool,
 }
 #[tracing::instrument(skip_all)]
-async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<NewsletterIssue, anyhow::Error> {
-    let issue = sqlx::query_as!(
-        NewsletterIssue,
+async fn get_issue(
+    transaction: &mut PgTransaction,
+    issue_id: Uuid,
+) -> Result<NewsletterIssue, anyhow::Error> {
+    let issue = sqlx::query!(
         r#"
-        SELECT title, text_content, html_content
+        SELECT title, text_content, html_content, track_opens, track_clicks
         FROM newsletter_issues
         WHERE
             newsletter_issue_id = $1 "#,
         issue_id
     )
-    .fetch_one(pool)
+    .fetch_one(transaction)
     .await?;
-    Ok(issue)
+    Ok(NewsletterIssue {
+        title: issue.title,
+        text_content: parse_template(&issue.text_content),
+        html_content: parse_template(&issue.html_content),
+        track_opens: issue.track_opens,
+        track_clicks: issue.track_clicks,
+    })
+}
+
+/// Templates are validated when an issue is published: content that does
+/// not parse predates merge tags and is sent as is.
+fn parse_template(content: &str) -> IssueTemplate {
+    IssueTemplate::parse(content).unwrap_or_else(|e| {
+        tracing::warn!(error.message = %e, "Sending an issue without merge tags.");
+        IssueTemplate::literal(content)
+    })
 }
 
-async fn worker_loop(pool: PgPool, email_client: EmailClient) -> Result<(), anyhow::Error> {
+pub async fn worker_loop(
+    pool: PgPool,
+    email_client: EmailClient,
+    base_url: ApplicationBaseUrl,
+    hmac_secret: HmacSecret,
+    settings: IssueDeliverySettings,
+    rate_limiter: RateLimiter,
+) -> Result<(), anyhow::Error> {
+    // Without a listener we can still deliver everything, just more slowly.
+    let mut listener = match listen_for_new_tasks(&pool).await {
+        Ok(listener) => Some(listener),
+        Err(e) => {
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to listen for new delivery tasks. Falling back to polling.",
+            );
+            None
+        }
+    };
     loop {
-        match try_execute_task(&pool, &email_client).await {
+        match try_execute_task(
+            &pool,
+            &email_client,
+            &base_url,
+            &hmac_secret,
+            &settings,
+            &rate_limiter,
+        )
+        .await
+        {
             Ok(ExecutionOutcome::EmptyQueue) => {
-                tokio::time::sleep(Duration::from_secs(10)).await;
+                wait_for_new_tasks(listener.as_mut(), settings.poll_interval()).await;
             }
             Err(_) => {
                 tokio::time::sleep(Duration::from_secs(1)).await;
@@ -157,10 +630,82 @@ async fn worker_loop(pool: PgPool, email_client: EmailClient) -> Result<(), anyh
     }
 }
 
+/// Publish the scheduled issues as they fall due, checking every
+/// `check_interval`. A single scheduler runs next to the workers.
+pub async fn scheduler_loop(pool: PgPool, check_interval: Duration) -> Result<(), anyhow::Error> {
+    let mut interval = tokio::time::interval(check_interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        if let Err(e) = publish_due_issues(&pool).await {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to publish the scheduled issues. Trying again later.",
+            );
+        }
+    }
+}
+
+async fn listen_for_new_tasks(pool: &PgPool) -> Result<PgListener, sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(ISSUE_DELIVERY_CHANNEL).await?;
+    Ok(listener)
+}
+
+/// Block until we are notified of new tasks or `poll_interval` has elapsed,
+/// whichever comes first.
+async fn wait_for_new_tasks(listener: Option<&mut PgListener>, poll_interval: Duration) {
+    let Some(listener) = listener else {
+        tokio::time::sleep(poll_interval).await;
+        return;
+    };
+    tokio::select! {
+        notification = listener.recv() => {
+            if let Err(e) = notification {
+                // The listener reconnects on the next `recv`; back off a
+                // little to avoid spinning if Postgres is unavailable.
+                tracing::warn!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to receive delivery queue notifications.",
+                );
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+        _ = tokio::time::sleep(poll_interval) => {}
+    }
+}
+
 pub async fn run_worker_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
     let connection_pool = get_connection_pool(&configuration.database);
 
     let email_client = configuration.email_client.client();
+    let base_url = ApplicationBaseUrl(configuration.application.base_url);
+    let hmac_secret = HmacSecret(configuration.application.hmac_secret);
+    let settings = configuration.issue_delivery;
+    // Shared by all workers: the provider quota applies to all of them.
+    let rate_limiter = RateLimiter::new(settings.max_sends_per_second);
 
-    worker_loop(connection_pool, email_client).await
+    let mut workers = JoinSet::new();
+    workers.spawn(scheduler_loop(
+        connection_pool.clone(),
+        settings.poll_interval(),
+    ));
+    for _ in 0..settings.concurrency.max(1) {
+        workers.spawn(worker_loop(
+            connection_pool.clone(),
+            email_client.clone(),
+            base_url.clone(),
+            hmac_secret.clone(),
+            settings.clone(),
+            rate_limiter.clone(),
+        ));
+    }
+    // Workers and the scheduler only return on failure: bring everything
+    // down with the first one.
+    match workers.join_next().await {
+        Some(outcome) => outcome?,
+        None => Ok(()),
+    }
 }
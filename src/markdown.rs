@@ -0,0 +1,146 @@
+use pulldown_cmark::{escape::escape_html, html, CowStr, Event, Options, Parser, Tag};
+
+fn parser(markdown: &str) -> Parser<'_, '_> {
+    Parser::new_ext(
+        markdown,
+        Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES,
+    )
+}
+
+/// Render Markdown to HTML, stripping anything unsafe (raw `<script>`s,
+/// event handler attributes, `javascript:` links, ...).
+pub fn render_html(markdown: &str) -> String {
+    let events = parser(markdown).map(|event| match event {
+        // The HTML renderer percent-encodes braces in link targets, which
+        // would turn `[Unsubscribe]({{ unsubscribe_url }})` into a dead link
+        // and hide the merge tag: open such links ourselves.
+        Event::Start(Tag::Link(_, destination, title)) if destination.contains("{{") => {
+            Event::Html(merge_tag_link(&destination, &title).into())
+        }
+        event => event,
+    });
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, events);
+    ammonia::clean(&unsafe_html)
+}
+
+/// The opening `<a>` tag of a link whose target contains merge tags,
+/// HTML-escaped but otherwise left as written.
+fn merge_tag_link(destination: &CowStr, title: &CowStr) -> String {
+    let mut tag = String::from("<a href=\"");
+    escape_html(&mut tag, destination).expect("Writing to a String cannot fail.");
+    if !title.is_empty() {
+        tag.push_str("\" title=\"");
+        escape_html(&mut tag, title).expect("Writing to a String cannot fail.");
+    }
+    tag.push_str("\">");
+    tag
+}
+
+/// Render Markdown to plain text, for the text version of an email:
+/// markup is dropped, lists keep their bullets and links keep their target.
+pub fn render_text(markdown: &str) -> String {
+    let mut text = String::new();
+    // The next number of each (nested) list, `None` for bullet lists.
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    for event in parser(markdown) {
+        match event {
+            Event::Start(Tag::List(first_number)) => {
+                if !text.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                lists.push(first_number);
+            }
+            Event::End(Tag::List(_)) => {
+                lists.pop();
+                if lists.is_empty() {
+                    text.push('\n');
+                }
+            }
+            Event::Start(Tag::Item) => {
+                text.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(Some(number)) => {
+                        text.push_str(&format!("{}. ", number));
+                        *number += 1;
+                    }
+                    _ => text.push_str("- "),
+                }
+            }
+            Event::End(Tag::Item) if !text.ends_with('\n') => text.push('\n'),
+            Event::End(Tag::Paragraph) if !lists.is_empty() => text.push('\n'),
+            Event::End(Tag::Paragraph | Tag::Heading(..) | Tag::CodeBlock(_) | Tag::BlockQuote) => {
+                while !text.ends_with("\n\n") {
+                    text.push('\n');
+                }
+            }
+            // Autolinks already show their target.
+            Event::End(Tag::Link(_, destination, _) | Tag::Image(_, destination, _))
+                if !text.ends_with(destination.as_ref()) =>
+            {
+                text.push_str(&format!(" ({})", destination));
+            }
+            Event::Text(s) | Event::Code(s) => text.push_str(&s),
+            Event::SoftBreak | Event::HardBreak => text.push('\n'),
+            Event::Rule => text.push_str("---\n\n"),
+            _ => {}
+        }
+    }
+    text.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_html, render_text};
+
+    #[test]
+    fn markdown_is_rendered_to_html() {
+        let html = render_html("# Hello\n\nSome **bold** [link](https://example.com).");
+        assert!(html.contains("<h1>Hello</h1>"));
+        assert!(html.contains("<strong>bold</strong>"));
+        assert!(html.contains(r#"href="https://example.com""#));
+    }
+
+    #[test]
+    fn unsafe_html_is_stripped() {
+        let html = render_html(
+            "<script>alert(1)</script>\n\n<a href=\"javascript:alert(1)\" onclick=\"x()\">hi</a>",
+        );
+        assert!(!html.contains("script"));
+        assert!(!html.contains("onclick"));
+        assert!(!html.contains("javascript:"));
+    }
+
+    #[test]
+    fn markdown_is_rendered_to_plain_text() {
+        let text = render_text(
+            "# Hello\n\nSome **bold** [link](https://example.com).\n\n\
+            - one\n- two\n  1. nested\n\n<https://example.com/auto>\n\n`code` &amp; more",
+        );
+        assert_eq!(
+            text,
+            "Hello\n\n\
+            Some bold link (https://example.com).\n\n\
+            - one\n- two\n  1. nested\n\n\
+            https://example.com/auto\n\n\
+            code & more"
+        );
+    }
+
+    #[test]
+    fn merge_tags_survive_rendering() {
+        assert_eq!(render_text("Hi {{ name }}!"), "Hi {{ name }}!");
+        assert!(render_html("Hi {{ name }}!").contains("Hi {{ name }}!"));
+    }
+
+    #[test]
+    fn merge_tags_survive_in_link_targets() {
+        let html = render_html(
+            "[Unsubscribe]({{unsubscribe_url}}) or [leave](<{{ unsubscribe_url }}> \"Bye & thanks\")",
+        );
+        assert!(html.contains(r#"href="{{unsubscribe_url}}""#));
+        assert!(html.contains(r#"href="{{ unsubscribe_url }}""#));
+        assert!(html.contains(r#"title="Bye &amp; thanks""#));
+        assert!(html.contains(">Unsubscribe</a>"));
+    }
+}
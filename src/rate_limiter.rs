@@ -0,0 +1,71 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Spread operations evenly over time, at most `max_per_second` of them.
+///
+/// Clones share the same budget: hand a clone to every task that must
+/// collectively stay under the limit.
+#[derive(Clone)]
+pub struct RateLimiter {
+    interval: Duration,
+    next_slot: Arc<Mutex<Instant>>,
+}
+
+impl RateLimiter {
+    pub fn new(max_per_second: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / max_per_second.max(1),
+            next_slot: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Wait until we are allowed to perform one more operation.
+    pub async fn acquire(&self) {
+        self.acquire_many(1).await
+    }
+
+    /// Wait until we are allowed to perform `n` more operations at once.
+    pub async fn acquire_many(&self, n: usize) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let first_slot = (*next_slot).max(Instant::now());
+            let last_slot = first_slot + self.interval * n.saturating_sub(1) as u32;
+            *next_slot = last_slot + self.interval;
+            last_slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RateLimiter;
+    use std::time::{Duration, Instant};
+
+    #[tokio::test]
+    async fn the_first_operation_is_not_delayed() {
+        let limiter = RateLimiter::new(1);
+        let start = Instant::now();
+        limiter.acquire().await;
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn clones_share_the_same_budget() {
+        let limiter = RateLimiter::new(20);
+        let start = Instant::now();
+        let handles: Vec<_> = (0..5)
+            .map(|_| {
+                let limiter = limiter.clone();
+                tokio::spawn(async move { limiter.acquire().await })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        // The fifth operation has to wait for four 50ms intervals.
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+}
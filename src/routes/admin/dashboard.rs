@@ -46,6 +46,9 @@ pub async fn admin_dashboard(
     <p>Available actions:</p>
     <ol>
         <li><a href="/admin/newsletter">Send newsletter</a></li>
+        <li><a href="/admin/issues">Newsletter issues</a></li>
+        <li><a href="/admin/subscribers">Subscribers</a></li>
+        <li><a href="/admin/dead_letters">Failed deliveries</a></li>
         <li><a href="/admin/password">Change password</a></li>
         <li>
           <form name="logoutForm" action="/admin/logout" method="post">
@@ -0,0 +1,118 @@
+use anyhow::Context;
+use axum::{
+    extract::State,
+    response::{Html, IntoResponse},
+};
+use axum_extra::extract::SignedCookieJar;
+use chrono::{DateTime, Utc};
+use cookie::Cookie;
+use htmlescape::encode_minimal;
+use uuid::Uuid;
+
+use crate::routes::admin::dashboard::AdminDashboardError;
+
+struct DeadLetter {
+    newsletter_issue_id: Uuid,
+    title: String,
+    subscriber_email: String,
+    n_retries: i32,
+    last_error: String,
+    failed_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(skip_all, err(Debug))]
+pub async fn dead_letters(
+    State(pool): State<sqlx::PgPool>,
+    signed_jar: SignedCookieJar,
+) -> Result<axum::response::Response, AdminDashboardError> {
+    let flash_html = match signed_jar.get("_flash") {
+        None => "".into(),
+        Some(cookie) => {
+            format!("<p><i>{}</i></p>", cookie.value())
+        }
+    };
+
+    let rows = get_dead_letters(&pool)
+        .await
+        .map_err(AdminDashboardError::UnexpectedError)?
+        .into_iter()
+        .map(|dead_letter| {
+            format!(
+                r#"<tr>
+            <td><a href="/admin/issues/{issue_id}">{title}</a></td>
+            <td>{email}</td>
+            <td>{n_retries}</td>
+            <td>{last_error}</td>
+            <td>{failed_at}</td>
+            <td>
+                <form action="/admin/dead_letters/requeue" method="post">
+                    <input hidden type="text" name="newsletter_issue_id" value="{issue_id}">
+                    <input hidden type="text" name="subscriber_email" value="{email}">
+                    <button type="submit">Requeue</button>
+                </form>
+            </td>
+        </tr>"#,
+                title = encode_minimal(&dead_letter.title),
+                email = encode_minimal(&dead_letter.subscriber_email),
+                n_retries = dead_letter.n_retries,
+                last_error = encode_minimal(&dead_letter.last_error),
+                failed_at = dead_letter.failed_at.to_rfc3339(),
+                issue_id = dead_letter.newsletter_issue_id,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok((
+        signed_jar.remove(Cookie::build("_flash", "").path("/admin").finish()),
+        Html(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Failed deliveries</title>
+</head>
+<body>
+    {flash_html}
+    <p>Deliveries that ran out of retries:</p>
+    <table>
+        <tr>
+            <th>Issue</th>
+            <th>Subscriber</th>
+            <th>Retries</th>
+            <th>Last error</th>
+            <th>Failed at</th>
+            <th></th>
+        </tr>
+        {rows}
+    </table>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+        )),
+    )
+        .into_response())
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_dead_letters(pool: &sqlx::PgPool) -> Result<Vec<DeadLetter>, anyhow::Error> {
+    let dead_letters = sqlx::query_as!(
+        DeadLetter,
+        r#"
+        SELECT
+            d.newsletter_issue_id,
+            i.title,
+            d.subscriber_email,
+            d.n_retries,
+            d.last_error,
+            d.failed_at
+        FROM issue_delivery_dead_letters d
+        JOIN newsletter_issues i USING (newsletter_issue_id)
+        ORDER BY d.failed_at DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the dead-lettered deliveries.")?;
+    Ok(dead_letters)
+}
@@ -0,0 +1,5 @@
+mod get;
+pub use get::dead_letters;
+
+mod post;
+pub use post::requeue_dead_letter;
@@ -0,0 +1,118 @@
+use anyhow::Context;
+use axum::{
+    extract::State,
+    response::{IntoResponse, Redirect},
+    Form,
+};
+use axum_extra::extract::SignedCookieJar;
+use cookie::Cookie;
+use uuid::Uuid;
+
+use crate::{issue_delivery_worker::notify_workers, routes::admin::dashboard::AdminDashboardError};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    newsletter_issue_id: Uuid,
+    subscriber_email: String,
+}
+
+#[tracing::instrument(
+    skip_all,
+    fields(newsletter_issue_id=%form.newsletter_issue_id, subscriber_email=%form.subscriber_email),
+    err(Debug)
+)]
+pub async fn requeue_dead_letter(
+    State(pool): State<sqlx::PgPool>,
+    signed_jar: SignedCookieJar,
+    Form(form): Form<FormData>,
+) -> Result<axum::response::Response, AdminDashboardError> {
+    let outcome = requeue(&pool, form.newsletter_issue_id, &form.subscriber_email)
+        .await
+        .map_err(AdminDashboardError::UnexpectedError)?;
+
+    let subscriber_email = htmlescape::encode_minimal(&form.subscriber_email);
+    let message = match outcome {
+        RequeueOutcome::Requeued => {
+            format!("The delivery to {} has been requeued.", subscriber_email)
+        }
+        RequeueOutcome::NotConfirmed => format!(
+            "The delivery to {} was not requeued: subscriber is no longer confirmed.",
+            subscriber_email
+        ),
+        RequeueOutcome::NoDeadLetter => "There was no failed delivery to requeue.".into(),
+    };
+    Ok((
+        signed_jar.add(Cookie::build("_flash", message).path("/admin").finish()),
+        Redirect::to("/admin/dead_letters"),
+    )
+        .into_response())
+}
+
+enum RequeueOutcome {
+    Requeued,
+    NoDeadLetter,
+    NotConfirmed,
+}
+
+/// Move a dead-lettered task back into the delivery queue, with a fresh
+/// retry budget.
+/// The dead letter is kept if the subscriber is no longer confirmed: they
+/// might have unsubscribed, bounced or been suspended since.
+#[tracing::instrument(skip(pool))]
+async fn requeue(
+    pool: &sqlx::PgPool,
+    newsletter_issue_id: Uuid,
+    subscriber_email: &str,
+) -> Result<RequeueOutcome, anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let n_deleted_rows = sqlx::query!(
+        r#"
+        DELETE FROM issue_delivery_dead_letters
+        WHERE
+            newsletter_issue_id = $1 AND
+            subscriber_email = $2
+        "#,
+        newsletter_issue_id,
+        subscriber_email
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to delete the dead letter.")?
+    .rows_affected();
+    if n_deleted_rows == 0 {
+        return Ok(RequeueOutcome::NoDeadLetter);
+    }
+    let n_inserted_rows = sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (
+            newsletter_issue_id,
+            subscriber_email
+        )
+        SELECT $1, email
+        FROM subscriptions
+        WHERE email = $2 AND status = 'confirmed'
+        ON CONFLICT DO NOTHING
+        "#,
+        newsletter_issue_id,
+        subscriber_email
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to requeue the delivery task.")?
+    .rows_affected();
+    if n_inserted_rows == 0 {
+        // Dropping the transaction rolls the deletion back.
+        return Ok(RequeueOutcome::NotConfirmed);
+    }
+    notify_workers(&mut transaction)
+        .await
+        .context("Failed to notify the delivery workers.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to requeue a delivery task.")?;
+    Ok(RequeueOutcome::Requeued)
+}
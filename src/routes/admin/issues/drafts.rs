@@ -0,0 +1,265 @@
+use anyhow::Context;
+use axum::{
+    extract::{Path, State},
+    response::{Html, IntoResponse, Redirect},
+    Form,
+};
+use axum_extra::extract::SignedCookieJar;
+use cookie::Cookie;
+use htmlescape::encode_minimal;
+use uuid::Uuid;
+
+use crate::routes::admin::dashboard::AdminDashboardError;
+
+#[derive(serde::Deserialize, Default)]
+pub struct DraftFormData {
+    title: String,
+    html_content: String,
+    text_content: String,
+    #[serde(default)]
+    track_opens: bool,
+    #[serde(default)]
+    track_clicks: bool,
+}
+
+struct Draft {
+    title: String,
+    html_content: String,
+    text_content: String,
+    track_opens: bool,
+    track_clicks: bool,
+    status: String,
+}
+
+pub async fn new_draft_form(signed_jar: SignedCookieJar) -> impl IntoResponse {
+    draft_page(
+        signed_jar,
+        "New draft",
+        "/admin/issues",
+        &DraftFormData::default(),
+    )
+}
+
+#[tracing::instrument(skip(pool, signed_jar, form), err(Debug))]
+pub async fn create_draft(
+    State(pool): State<sqlx::PgPool>,
+    signed_jar: SignedCookieJar,
+    Form(form): Form<DraftFormData>,
+) -> Result<axum::response::Response, AdminDashboardError> {
+    let newsletter_issue_id = insert_draft(&pool, &form)
+        .await
+        .map_err(AdminDashboardError::UnexpectedError)?;
+    Ok((
+        signed_jar.add(
+            Cookie::build("_flash", "The draft has been saved.")
+                .path("/admin")
+                .finish(),
+        ),
+        Redirect::to(&format!("/admin/issues/{}/edit", newsletter_issue_id)),
+    )
+        .into_response())
+}
+
+#[tracing::instrument(skip(pool, signed_jar), err(Debug))]
+pub async fn edit_draft_form(
+    State(pool): State<sqlx::PgPool>,
+    signed_jar: SignedCookieJar,
+    Path(newsletter_issue_id): Path<Uuid>,
+) -> Result<axum::response::Response, AdminDashboardError> {
+    let draft = match get_draft(&pool, newsletter_issue_id)
+        .await
+        .map_err(AdminDashboardError::UnexpectedError)?
+    {
+        Some(draft) => draft,
+        None => return Ok(axum::http::StatusCode::NOT_FOUND.into_response()),
+    };
+    if draft.status != "draft" {
+        return Ok(Redirect::to(&format!("/admin/issues/{}", newsletter_issue_id)).into_response());
+    }
+    Ok(draft_page(
+        signed_jar,
+        "Edit draft",
+        &format!("/admin/issues/{}/edit", newsletter_issue_id),
+        &DraftFormData {
+            title: draft.title,
+            html_content: draft.html_content,
+            text_content: draft.text_content,
+            track_opens: draft.track_opens,
+            track_clicks: draft.track_clicks,
+        },
+    )
+    .into_response())
+}
+
+#[tracing::instrument(skip(pool, signed_jar, form), err(Debug))]
+pub async fn update_draft(
+    State(pool): State<sqlx::PgPool>,
+    signed_jar: SignedCookieJar,
+    Path(newsletter_issue_id): Path<Uuid>,
+    Form(form): Form<DraftFormData>,
+) -> Result<axum::response::Response, AdminDashboardError> {
+    let updated = update(&pool, newsletter_issue_id, &form)
+        .await
+        .map_err(AdminDashboardError::UnexpectedError)?;
+    let (message, location) = if updated {
+        (
+            "The draft has been saved.",
+            format!("/admin/issues/{}/edit", newsletter_issue_id),
+        )
+    } else {
+        (
+            "Only drafts can be edited.",
+            format!("/admin/issues/{}", newsletter_issue_id),
+        )
+    };
+    Ok((
+        signed_jar.add(Cookie::build("_flash", message).path("/admin").finish()),
+        Redirect::to(&location),
+    )
+        .into_response())
+}
+
+fn draft_page(
+    signed_jar: SignedCookieJar,
+    heading: &str,
+    action: &str,
+    draft: &DraftFormData,
+) -> impl IntoResponse {
+    let flash_html = match signed_jar.get("_flash") {
+        None => "".into(),
+        Some(cookie) => {
+            format!("<p><i>{}</i></p>", cookie.value())
+        }
+    };
+    let title = encode_minimal(&draft.title);
+    let html_content = encode_minimal(&draft.html_content);
+    let text_content = encode_minimal(&draft.text_content);
+    let checked = |enabled: bool| if enabled { " checked" } else { "" };
+    let track_opens = checked(draft.track_opens);
+    let track_clicks = checked(draft.track_clicks);
+    let flash_cookie = Cookie::build("_flash", "").path("/admin").finish();
+
+    (
+        signed_jar.remove(flash_cookie),
+        Html(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>{heading}</title>
+</head>
+<body>
+    {flash_html}
+    <h1>{heading}</h1>
+    <form action="{action}" method="post">
+        <label>Title
+            <input type="text" name="title" value="{title}">
+        </label>
+        <br>
+        <label>HTML content
+            <textarea name="html_content">{html_content}</textarea>
+        </label>
+        <br>
+        <label>TEXT content
+            <textarea name="text_content">{text_content}</textarea>
+        </label>
+        <br>
+        <label>
+            <input type="checkbox" name="track_opens" value="true"{track_opens}>
+            Track opens
+        </label>
+        <label>
+            <input type="checkbox" name="track_clicks" value="true"{track_clicks}>
+            Track clicks
+        </label>
+        <br>
+        <button type="submit">Save draft</button>
+    </form>
+    <p><a href="/admin/issues">&lt;- Back</a></p>
+</body>
+</html>"#,
+        )),
+    )
+}
+
+#[tracing::instrument(skip_all)]
+async fn insert_draft(pool: &sqlx::PgPool, form: &DraftFormData) -> Result<Uuid, anyhow::Error> {
+    let newsletter_issue_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issues (
+            newsletter_issue_id,
+            title,
+            text_content,
+            html_content,
+            track_opens,
+            track_clicks,
+            status
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, 'draft')
+        "#,
+        newsletter_issue_id,
+        form.title,
+        form.text_content,
+        form.html_content,
+        form.track_opens,
+        form.track_clicks,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the draft.")?;
+    Ok(newsletter_issue_id)
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_draft(
+    pool: &sqlx::PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<Option<Draft>, anyhow::Error> {
+    let draft = sqlx::query_as!(
+        Draft,
+        r#"
+        SELECT title, html_content, text_content, track_opens, track_clicks, status
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the draft.")?;
+    Ok(draft)
+}
+
+/// Returns `false` if the issue is not a draft (anymore).
+#[tracing::instrument(skip(pool, form))]
+async fn update(
+    pool: &sqlx::PgPool,
+    newsletter_issue_id: Uuid,
+    form: &DraftFormData,
+) -> Result<bool, anyhow::Error> {
+    let n_updated_rows = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            title = $2,
+            text_content = $3,
+            html_content = $4,
+            track_opens = $5,
+            track_clicks = $6,
+            updated_at = now()
+        WHERE newsletter_issue_id = $1 AND status = 'draft'
+        "#,
+        newsletter_issue_id,
+        form.title,
+        form.text_content,
+        form.html_content,
+        form.track_opens,
+        form.track_clicks,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to update the draft.")?
+    .rows_affected();
+    Ok(n_updated_rows > 0)
+}
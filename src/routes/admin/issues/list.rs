@@ -0,0 +1,99 @@
+use anyhow::Context;
+use axum::{
+    extract::State,
+    response::{Html, IntoResponse},
+};
+use axum_extra::extract::SignedCookieJar;
+use chrono::{DateTime, Utc};
+use cookie::Cookie;
+use htmlescape::encode_minimal;
+use uuid::Uuid;
+
+use crate::routes::admin::dashboard::AdminDashboardError;
+
+struct IssueSummary {
+    newsletter_issue_id: Uuid,
+    title: String,
+    status: String,
+    updated_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(skip(pool, signed_jar), err(Debug))]
+pub async fn list_issues(
+    State(pool): State<sqlx::PgPool>,
+    signed_jar: SignedCookieJar,
+) -> Result<axum::response::Response, AdminDashboardError> {
+    let flash_html = match signed_jar.get("_flash") {
+        None => "".into(),
+        Some(cookie) => {
+            format!("<p><i>{}</i></p>", cookie.value())
+        }
+    };
+    let issues = get_issues(&pool)
+        .await
+        .map_err(AdminDashboardError::UnexpectedError)?;
+
+    let rows = if issues.is_empty() {
+        "<tr><td colspan=\"4\">No newsletter issues yet.</td></tr>".to_string()
+    } else {
+        issues
+            .iter()
+            .map(|issue| {
+                let actions = if issue.status == "draft" {
+                    format!(
+                        r#"<a href="/admin/issues/{id}/edit">Edit</a> <a href="/admin/issues/{id}/preview">Preview</a>"#,
+                        id = issue.newsletter_issue_id
+                    )
+                } else {
+                    "".into()
+                };
+                format!(
+                    r#"<tr><td><a href="/admin/issues/{}">{}</a></td><td>{}</td><td>{}</td><td>{}</td></tr>"#,
+                    issue.newsletter_issue_id,
+                    encode_minimal(&issue.title),
+                    issue.status,
+                    issue.updated_at.format("%Y-%m-%d %H:%M UTC"),
+                    actions
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n        ")
+    };
+
+    let body = Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Newsletter issues</title>
+</head>
+<body>
+    {flash_html}
+    <p><a href="/admin/issues/new">New draft</a></p>
+    <table>
+        <tr><th>Title</th><th>Status</th><th>Last edited</th><th></th></tr>
+        {rows}
+    </table>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+    ));
+    let flash_cookie = Cookie::build("_flash", "").path("/admin").finish();
+    Ok((signed_jar.remove(flash_cookie), body).into_response())
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_issues(pool: &sqlx::PgPool) -> Result<Vec<IssueSummary>, anyhow::Error> {
+    let issues = sqlx::query_as!(
+        IssueSummary,
+        r#"
+        SELECT newsletter_issue_id, title, status, updated_at
+        FROM newsletter_issues
+        ORDER BY updated_at DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the newsletter issues.")?;
+    Ok(issues)
+}
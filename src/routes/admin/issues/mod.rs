@@ -0,0 +1,14 @@
+mod drafts;
+mod list;
+mod preview;
+mod publish;
+mod report;
+mod schedule;
+mod test_send;
+pub use drafts::*;
+pub use list::list_issues;
+pub use preview::preview_issue;
+pub use publish::*;
+pub use report::issue_report;
+pub use schedule::*;
+pub use test_send::*;
@@ -0,0 +1,80 @@
+use anyhow::Context;
+use axum::{
+    extract::{Path, State},
+    response::{Html, IntoResponse},
+};
+use htmlescape::{encode_attribute, encode_minimal};
+use uuid::Uuid;
+
+use crate::{email_html, routes::admin::dashboard::AdminDashboardError};
+
+pub(super) struct IssueContent {
+    pub(super) title: String,
+    pub(super) html_content: String,
+    pub(super) text_content: String,
+}
+
+/// Show an issue the way subscribers will see it, both as HTML and as text.
+#[tracing::instrument(skip(pool), err(Debug))]
+pub async fn preview_issue(
+    State(pool): State<sqlx::PgPool>,
+    Path(newsletter_issue_id): Path<Uuid>,
+) -> Result<axum::response::Response, AdminDashboardError> {
+    let issue = match get_issue_content(&pool, newsletter_issue_id)
+        .await
+        .map_err(AdminDashboardError::UnexpectedError)?
+    {
+        Some(issue) => issue,
+        None => return Ok(axum::http::StatusCode::NOT_FOUND.into_response()),
+    };
+    let title = encode_minimal(&issue.title);
+    // The issue is rendered in a sandboxed frame, so that its markup and
+    // styles cannot leak into (or script) the admin pages.
+    let html_html = match email_html::prepare(&issue.html_content) {
+        Ok(html_content) => format!(
+            r#"<iframe sandbox srcdoc="{}" width="100%" height="600"></iframe>"#,
+            encode_attribute(&html_content)
+        ),
+        Err(e) => format!("<p><i>{}</i></p>", encode_minimal(&e)),
+    };
+    let text_content = encode_minimal(&issue.text_content);
+
+    Ok(Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Preview</title>
+</head>
+<body>
+    <h1>{title}</h1>
+    <h2>HTML</h2>
+    {html_html}
+    <h2>Text</h2>
+    <pre>{text_content}</pre>
+    <p><a href="/admin/issues/{newsletter_issue_id}">&lt;- Back</a></p>
+</body>
+</html>"#,
+    ))
+    .into_response())
+}
+
+#[tracing::instrument(skip(pool))]
+pub(super) async fn get_issue_content(
+    pool: &sqlx::PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<Option<IssueContent>, anyhow::Error> {
+    let issue = sqlx::query_as!(
+        IssueContent,
+        r#"
+        SELECT title, html_content, text_content
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the newsletter issue.")?;
+    Ok(issue)
+}
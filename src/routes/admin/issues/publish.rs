@@ -0,0 +1,130 @@
+use anyhow::Context;
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Redirect},
+    Extension, Form,
+};
+use axum_extra::extract::SignedCookieJar;
+use chrono::Utc;
+use cookie::Cookie;
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+
+use super::preview::get_issue_content;
+use crate::{
+    authentication::UserId,
+    domain::SendAt,
+    email_html,
+    idempotency::{save_response, try_processing, IdempotencyKey, NextAction},
+    routes::admin::{
+        dashboard::AdminDashboardError,
+        newsletter::{parse_templates, send_or_schedule},
+    },
+};
+
+#[derive(serde::Deserialize)]
+pub struct PublishDraftFormData {
+    idempotency_key: String,
+    /// Leave empty to send the issue right away.
+    send_at: Option<String>,
+}
+
+#[tracing::instrument(
+    skip(pool, signed_jar, form, user_id),
+    fields(user_id=%&*user_id),
+    err(Debug)
+)]
+pub async fn publish_draft(
+    State(pool): State<sqlx::PgPool>,
+    Extension(user_id): Extension<UserId>,
+    signed_jar: SignedCookieJar,
+    Path(newsletter_issue_id): Path<Uuid>,
+    Form(form): Form<PublishDraftFormData>,
+) -> Result<axum::response::Response, AdminDashboardError> {
+    let issue_page = format!("/admin/issues/{}", newsletter_issue_id);
+    let idempotency_key: IdempotencyKey = form.idempotency_key.try_into()?;
+    let issue = match get_issue_content(&pool, newsletter_issue_id)
+        .await
+        .map_err(AdminDashboardError::UnexpectedError)?
+    {
+        Some(issue) => issue,
+        None => return Ok(axum::http::StatusCode::NOT_FOUND.into_response()),
+    };
+    // Drafts keep the HTML as written; the processed version is what goes out.
+    let (html_content, send_at) =
+        match email_html::prepare(&issue.html_content).and_then(|html_content| {
+            parse_templates(&html_content, &issue.text_content)?;
+            let send_at = SendAt::parse_optional(form.send_at.as_deref())?;
+            Ok((html_content, send_at))
+        }) {
+            Ok(content) => content,
+            Err(e) => {
+                let message = htmlescape::encode_minimal(&e);
+                return Ok((
+                    signed_jar.add(Cookie::build("_flash", message).path("/admin").finish()),
+                    Redirect::to(&issue_page),
+                )
+                    .into_response());
+            }
+        };
+
+    let mut transaction = match try_processing(&pool, &idempotency_key, *user_id).await? {
+        NextAction::StartProcessing(t) => t,
+        NextAction::ReturnSavedResponse(saved_response) => return Ok(saved_response),
+    };
+
+    let published = publish(
+        &mut transaction,
+        newsletter_issue_id,
+        &html_content,
+        send_at.as_ref(),
+    )
+    .await
+    .context("Failed to publish the draft")?;
+    let message = if published {
+        send_or_schedule(&mut transaction, newsletter_issue_id, send_at.as_ref()).await?
+    } else {
+        "Only drafts can be published.".into()
+    };
+
+    let response = (
+        signed_jar.add(Cookie::build("_flash", message).path("/admin").finish()),
+        Redirect::to(&issue_page),
+    )
+        .into_response();
+    let response = save_response(transaction, &idempotency_key, *user_id, response).await?;
+    Ok(response)
+}
+
+/// Turn a draft into an issue, published right away or held back until
+/// `send_at`. Returns `false` if the issue is not a draft (anymore).
+#[tracing::instrument(skip(transaction, html_content))]
+async fn publish(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    html_content: &str,
+    send_at: Option<&SendAt>,
+) -> Result<bool, sqlx::Error> {
+    let scheduled_for = send_at.map(|send_at| *send_at.as_ref());
+    let published_at = send_at.is_none().then(Utc::now);
+    let n_updated_rows = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            published_at = $4,
+            status = CASE WHEN $2::timestamptz IS NULL THEN 'published' ELSE 'scheduled' END,
+            scheduled_for = $2,
+            html_content = $3,
+            updated_at = now()
+        WHERE newsletter_issue_id = $1 AND status = 'draft'
+        "#,
+        newsletter_issue_id,
+        scheduled_for,
+        html_content,
+        published_at
+    )
+    .execute(transaction)
+    .await?
+    .rows_affected();
+    Ok(n_updated_rows > 0)
+}
@@ -0,0 +1,192 @@
+use anyhow::Context;
+use axum::{
+    extract::{Path, State},
+    response::{Html, IntoResponse},
+};
+use axum_extra::extract::SignedCookieJar;
+use chrono::{DateTime, Utc};
+use cookie::Cookie;
+use htmlescape::encode_minimal;
+use uuid::Uuid;
+
+use crate::routes::admin::dashboard::AdminDashboardError;
+
+struct DeliveryReport {
+    title: String,
+    status: String,
+    published_at: Option<DateTime<Utc>>,
+    scheduled_for: Option<DateTime<Utc>>,
+    n_sent: i64,
+    n_failed: i64,
+    n_pending: i64,
+    track_opens: bool,
+    track_clicks: bool,
+    n_opened: i64,
+    n_clicked: i64,
+}
+
+#[tracing::instrument(skip(pool, signed_jar), err(Debug))]
+pub async fn issue_report(
+    State(pool): State<sqlx::PgPool>,
+    signed_jar: SignedCookieJar,
+    Path(newsletter_issue_id): Path<Uuid>,
+) -> Result<axum::response::Response, AdminDashboardError> {
+    let flash_html = match signed_jar.get("_flash") {
+        None => "".into(),
+        Some(cookie) => {
+            format!("<p><i>{}</i></p>", cookie.value())
+        }
+    };
+    let report = match get_delivery_report(&pool, newsletter_issue_id)
+        .await
+        .map_err(AdminDashboardError::UnexpectedError)?
+    {
+        Some(report) => report,
+        None => return Ok(axum::http::StatusCode::NOT_FOUND.into_response()),
+    };
+    let DeliveryReport {
+        title,
+        status,
+        published_at,
+        scheduled_for,
+        n_sent,
+        n_failed,
+        n_pending,
+        track_opens,
+        track_clicks,
+        n_opened,
+        n_clicked,
+    } = report;
+    let title = encode_minimal(&title);
+    let status_html = match (status.as_str(), scheduled_for) {
+        ("scheduled", Some(scheduled_for)) => format!(
+            r#"<p>Scheduled for {scheduled_for}</p>
+    <form action="/admin/issues/{newsletter_issue_id}/reschedule" method="post">
+        <label>Send at
+            <input type="datetime-local" name="send_at">
+        </label>
+        <button type="submit">Reschedule</button>
+    </form>
+    <form action="/admin/issues/{newsletter_issue_id}/cancel" method="post">
+        <button type="submit">Cancel</button>
+    </form>"#,
+            scheduled_for = scheduled_for.format("%Y-%m-%d %H:%M UTC"),
+        ),
+        ("draft", _) => format!(
+            r#"<p>Draft - <a href="/admin/issues/{newsletter_issue_id}/edit">Edit</a>
+        <a href="/admin/issues/{newsletter_issue_id}/preview">Preview</a></p>
+    <form action="/admin/issues/{newsletter_issue_id}/publish" method="post">
+        <label>Send at (UTC, leave empty to send right away)
+            <input type="datetime-local" name="send_at">
+        </label>
+        <input hidden type="text" name="idempotency_key" value="{idempotency_key}">
+        <button type="submit">Publish</button>
+    </form>"#,
+            idempotency_key = Uuid::new_v4(),
+        ),
+        ("cancelled", _) => "<p>Cancelled</p>".into(),
+        _ => format!(
+            "<p>Published at {}</p>",
+            published_at
+                .map(|published_at| published_at.format("%Y-%m-%d %H:%M UTC").to_string())
+                .unwrap_or_default()
+        ),
+    };
+    let mut engagement_html = String::new();
+    if track_opens {
+        engagement_html.push_str(&engagement_row("Opened", n_opened, n_sent));
+    }
+    if track_clicks {
+        engagement_html.push_str(&engagement_row("Clicked", n_clicked, n_sent));
+    }
+
+    let body = Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Delivery report</title>
+</head>
+<body>
+    {flash_html}
+    <h1>{title}</h1>
+    {status_html}
+    <form action="/admin/issues/{newsletter_issue_id}/test_send" method="post">
+        <label>Send a test copy to
+            <input type="text" name="recipients" placeholder="alice@example.com, bob@example.com">
+        </label>
+        <button type="submit">Send test</button>
+    </form>
+    <table>
+        <tr><th>Sent</th><td>{n_sent}</td></tr>
+        <tr><th>Failed</th><td>{n_failed}</td></tr>
+        <tr><th>Pending</th><td>{n_pending}</td></tr>
+        {engagement_html}
+    </table>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+    ));
+    let flash_cookie = Cookie::build("_flash", "").path("/admin").finish();
+    Ok((signed_jar.remove(flash_cookie), body).into_response())
+}
+
+/// How many recipients opened (or clicked through) the issue, counting each
+/// of them once however many times they did.
+fn engagement_row(label: &str, n_subscribers: i64, n_sent: i64) -> String {
+    let rate = if n_sent > 0 {
+        n_subscribers as f64 * 100.0 / n_sent as f64
+    } else {
+        0.0
+    };
+    format!(
+        "<tr><th>{}</th><td>{} ({:.1}% of sent)</td></tr>",
+        label, n_subscribers, rate
+    )
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_delivery_report(
+    pool: &sqlx::PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<Option<DeliveryReport>, anyhow::Error> {
+    let report = sqlx::query_as!(
+        DeliveryReport,
+        r#"
+        SELECT
+            i.title,
+            i.status,
+            i.published_at,
+            i.scheduled_for,
+            (
+                SELECT COUNT(*) FROM issue_delivery_log l
+                WHERE l.newsletter_issue_id = i.newsletter_issue_id AND l.outcome = 'sent'
+            ) AS "n_sent!",
+            (
+                SELECT COUNT(*) FROM issue_delivery_log l
+                WHERE l.newsletter_issue_id = i.newsletter_issue_id AND l.outcome = 'failed'
+            ) AS "n_failed!",
+            (
+                SELECT COUNT(*) FROM issue_delivery_queue q
+                WHERE q.newsletter_issue_id = i.newsletter_issue_id
+            ) AS "n_pending!",
+            i.track_opens,
+            i.track_clicks,
+            (
+                SELECT COUNT(DISTINCT o.subscriber_id) FROM issue_open_events o
+                WHERE o.newsletter_issue_id = i.newsletter_issue_id
+            ) AS "n_opened!",
+            (
+                SELECT COUNT(DISTINCT c.subscriber_id) FROM issue_click_events c
+                WHERE c.newsletter_issue_id = i.newsletter_issue_id
+            ) AS "n_clicked!"
+        FROM newsletter_issues i
+        WHERE i.newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to build the delivery report of a newsletter issue.")?;
+    Ok(report)
+}
@@ -0,0 +1,108 @@
+use anyhow::Context;
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Redirect},
+    Form,
+};
+use axum_extra::extract::SignedCookieJar;
+use cookie::Cookie;
+use htmlescape::encode_minimal;
+use uuid::Uuid;
+
+use crate::{domain::SendAt, routes::admin::dashboard::AdminDashboardError};
+
+#[derive(serde::Deserialize)]
+pub struct RescheduleFormData {
+    send_at: String,
+}
+
+#[tracing::instrument(skip(pool, signed_jar, form), err(Debug))]
+pub async fn reschedule_issue(
+    State(pool): State<sqlx::PgPool>,
+    signed_jar: SignedCookieJar,
+    Path(newsletter_issue_id): Path<Uuid>,
+    Form(form): Form<RescheduleFormData>,
+) -> Result<axum::response::Response, AdminDashboardError> {
+    let message = match SendAt::parse_future(&form.send_at) {
+        Err(e) => encode_minimal(&e),
+        Ok(send_at) => {
+            let rescheduled = reschedule(&pool, newsletter_issue_id, &send_at)
+                .await
+                .map_err(AdminDashboardError::UnexpectedError)?;
+            if rescheduled {
+                format!("The newsletter issue has been rescheduled for {}.", send_at)
+            } else {
+                "Only scheduled issues can be rescheduled.".into()
+            }
+        }
+    };
+    Ok((
+        // Set from a nested route: pin the path so that the pages
+        // displaying (and clearing) admin flash messages can see it.
+        signed_jar.add(Cookie::build("_flash", message).path("/admin").finish()),
+        Redirect::to(&format!("/admin/issues/{}", newsletter_issue_id)),
+    )
+        .into_response())
+}
+
+#[tracing::instrument(skip(pool, signed_jar), err(Debug))]
+pub async fn cancel_issue(
+    State(pool): State<sqlx::PgPool>,
+    signed_jar: SignedCookieJar,
+    Path(newsletter_issue_id): Path<Uuid>,
+) -> Result<axum::response::Response, AdminDashboardError> {
+    let cancelled = cancel(&pool, newsletter_issue_id)
+        .await
+        .map_err(AdminDashboardError::UnexpectedError)?;
+    let message = if cancelled {
+        "The newsletter issue has been cancelled."
+    } else {
+        "Only scheduled issues can be cancelled."
+    };
+    Ok((
+        signed_jar.add(Cookie::build("_flash", message).path("/admin").finish()),
+        Redirect::to(&format!("/admin/issues/{}", newsletter_issue_id)),
+    )
+        .into_response())
+}
+
+/// Returns `false` if the issue is not scheduled (anymore).
+#[tracing::instrument(skip(pool))]
+async fn reschedule(
+    pool: &sqlx::PgPool,
+    newsletter_issue_id: Uuid,
+    send_at: &SendAt,
+) -> Result<bool, anyhow::Error> {
+    let n_updated_rows = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET scheduled_for = $2
+        WHERE newsletter_issue_id = $1 AND status = 'scheduled'
+        "#,
+        newsletter_issue_id,
+        send_at.as_ref()
+    )
+    .execute(pool)
+    .await
+    .context("Failed to reschedule the newsletter issue.")?
+    .rows_affected();
+    Ok(n_updated_rows > 0)
+}
+
+/// Returns `false` if the issue is not scheduled (anymore).
+#[tracing::instrument(skip(pool))]
+async fn cancel(pool: &sqlx::PgPool, newsletter_issue_id: Uuid) -> Result<bool, anyhow::Error> {
+    let n_updated_rows = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = 'cancelled'
+        WHERE newsletter_issue_id = $1 AND status = 'scheduled'
+        "#,
+        newsletter_issue_id,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to cancel the newsletter issue.")?
+    .rows_affected();
+    Ok(n_updated_rows > 0)
+}
@@ -0,0 +1,119 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Redirect},
+    Form,
+};
+use axum_extra::extract::SignedCookieJar;
+use cookie::Cookie;
+use htmlescape::encode_minimal;
+use uuid::Uuid;
+
+use super::preview::get_issue_content;
+use crate::{
+    domain::{MergeFields, SubscriberEmail},
+    email_client::EmailClient,
+    email_html,
+    routes::admin::{dashboard::AdminDashboardError, newsletter::parse_templates},
+};
+
+/// A test copy is meant for a handful of internal addresses, not for a list.
+const MAX_TEST_RECIPIENTS: usize = 10;
+
+/// What `{{ name }}` renders to in test copies.
+const TEST_RECIPIENT_NAME: &str = "Test recipient";
+
+#[derive(serde::Deserialize)]
+pub struct TestSendFormData {
+    /// Separated by commas, spaces or new lines.
+    recipients: String,
+}
+
+/// Send a copy of an issue to the given addresses only.
+/// Nothing is enqueued: subscribers never see test copies.
+#[tracing::instrument(skip(pool, email_client, signed_jar, form), err(Debug))]
+pub async fn send_test_copy(
+    State(pool): State<sqlx::PgPool>,
+    State(email_client): State<EmailClient>,
+    signed_jar: SignedCookieJar,
+    Path(newsletter_issue_id): Path<Uuid>,
+    Form(form): Form<TestSendFormData>,
+) -> Result<axum::response::Response, AdminDashboardError> {
+    let issue = match get_issue_content(&pool, newsletter_issue_id)
+        .await
+        .map_err(AdminDashboardError::UnexpectedError)?
+    {
+        Some(issue) => issue,
+        None => return Ok(axum::http::StatusCode::NOT_FOUND.into_response()),
+    };
+
+    let message = match email_html::prepare(&issue.html_content)
+        .and_then(|html_content| parse_templates(&html_content, &issue.text_content))
+        .and_then(|templates| Ok((templates, parse_recipients(&form.recipients)?)))
+    {
+        Err(e) => encode_minimal(&e),
+        Ok(((html_template, text_template), recipients)) => {
+            let subject = format!("[Test] {}", issue.title);
+            let mut failed = Vec::new();
+            for recipient in &recipients {
+                // Test recipients are not subscribers: there is nobody to
+                // unsubscribe.
+                let fields = MergeFields {
+                    name: TEST_RECIPIENT_NAME,
+                    email: recipient.as_ref(),
+                    unsubscribe_url: "#",
+                };
+                if let Err(e) = email_client
+                    .send_email(
+                        recipient,
+                        &subject,
+                        &html_template.render_html(&fields),
+                        &text_template.render_text(&fields),
+                    )
+                    .await
+                {
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        "Failed to send a test copy to {}",
+                        recipient
+                    );
+                    failed.push(recipient.as_ref());
+                }
+            }
+            if failed.is_empty() {
+                format!(
+                    "A test copy has been sent to {} address(es).",
+                    recipients.len()
+                )
+            } else {
+                encode_minimal(&format!(
+                    "Failed to send a test copy to {}.",
+                    failed.join(", ")
+                ))
+            }
+        }
+    };
+    Ok((
+        signed_jar.add(Cookie::build("_flash", message).path("/admin").finish()),
+        Redirect::to(&format!("/admin/issues/{}", newsletter_issue_id)),
+    )
+        .into_response())
+}
+
+fn parse_recipients(recipients: &str) -> Result<Vec<SubscriberEmail>, String> {
+    let recipients = recipients
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| SubscriberEmail::parse(s.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    if recipients.is_empty() {
+        return Err("Enter at least one address to send the test copy to.".into());
+    }
+    if recipients.len() > MAX_TEST_RECIPIENTS {
+        return Err(format!(
+            "A test copy can be sent to at most {} addresses.",
+            MAX_TEST_RECIPIENTS
+        ));
+    }
+    Ok(recipients)
+}
@@ -1,9 +1,15 @@
 mod dashboard;
+mod dead_letters;
+mod issues;
 mod logout;
 mod newsletter;
 mod password;
+mod subscribers;
 
 pub use dashboard::admin_dashboard;
+pub use dead_letters::*;
+pub use issues::*;
 pub use logout::log_out;
 pub use newsletter::*;
 pub use password::*;
+pub use subscribers::*;
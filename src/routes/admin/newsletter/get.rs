@@ -31,20 +31,39 @@ pub async fn send_newsletter(signed_jar: SignedCookieJar) -> impl IntoResponse {
             >
         </label>
         <br>
+        <label>Markdown content
+            <textarea
+                placeholder="Write the issue in Markdown"
+                name="markdown_content"
+            ></textarea>
+        </label>
+        <br>
+        <p>Leave the fields below empty to render them from the Markdown content.</p>
         <label>HTML content
-            <input
-                type="text"
+            <textarea
                 placeholder="Enter html content"
                 name="html_content"
-            >
+            ></textarea>
         </label>
         <br>
         <label>TEXT content
-            <input
-                type="text"
+            <textarea
                 placeholder="Enter text content"
                 name="text_content"
-            >
+            ></textarea>
+        </label>
+        <br>
+        <label>Send at (UTC, leave empty to send right away)
+            <input type="datetime-local" name="send_at">
+        </label>
+        <br>
+        <label>
+            <input type="checkbox" name="track_opens" value="true">
+            Track opens
+        </label>
+        <label>
+            <input type="checkbox" name="track_clicks" value="true">
+            Track clicks
         </label>
         <br>
         <input hidden type="text" name="idempotency_key" value="{idempotency_key}">
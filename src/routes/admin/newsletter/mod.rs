@@ -3,3 +3,4 @@ pub use get::send_newsletter;
 
 mod post;
 pub use post::publish_newsletter;
+pub(crate) use post::{parse_templates, send_or_schedule};
@@ -7,6 +7,7 @@ use axum::{
     Form,
 };
 use axum_extra::extract::SignedCookieJar;
+use chrono::Utc;
 use cookie::Cookie;
 use sqlx::{Postgres, Transaction};
 use uuid::Uuid;
@@ -15,16 +16,28 @@ use uuid::Uuid;
 use crate::{
     //authentication::{Credentials, UserId},
     authentication::UserId,
+    domain::{IssueTemplate, SendAt},
+    email_html,
     idempotency::{save_response, try_processing, IdempotencyKey, NextAction},
+    issue_delivery_worker::{enqueue_delivery_tasks, notify_workers},
+    markdown,
     routes::error_chain_fmt,
 };
 
 #[derive(serde::Deserialize)]
 pub struct BodyData {
     title: String,
-    html_content: String,
-    text_content: String,
+    /// Renders both versions of the issue, unless they are given below.
+    markdown_content: Option<String>,
+    html_content: Option<String>,
+    text_content: Option<String>,
     idempotency_key: String,
+    /// Leave empty to send the issue right away.
+    send_at: Option<String>,
+    #[serde(default)]
+    track_opens: bool,
+    #[serde(default)]
+    track_clicks: bool,
 }
 #[derive(thiserror::Error)]
 pub enum PublishError {
@@ -105,12 +118,34 @@ pub async fn publish_newsletter(
 ) -> Result<Response, PublishError> {
     let BodyData {
         title,
+        markdown_content,
         text_content,
         html_content,
         idempotency_key,
+        send_at,
+        track_opens,
+        track_clicks,
     } = form;
 
     let idempotency_key: IdempotencyKey = idempotency_key.try_into()?;
+    let (html_content, text_content, send_at) =
+        match issue_content(markdown_content, html_content, text_content).and_then(
+            |(html_content, text_content)| {
+                let html_content = email_html::prepare(&html_content)?;
+                parse_templates(&html_content, &text_content)?;
+                let send_at = SendAt::parse_optional(send_at.as_deref())?;
+                Ok((html_content, text_content, send_at))
+            },
+        ) {
+            Ok(content) => content,
+            Err(e) => {
+                return Ok((
+                    signed_jar.add(Cookie::new("_flash", htmlescape::encode_minimal(&e))),
+                    Redirect::to("/admin/newsletter"),
+                )
+                    .into_response())
+            }
+        };
 
     let mut transaction = match try_processing(&pool, &idempotency_key, *user_id).await? {
         NextAction::StartProcessing(t) => t,
@@ -126,13 +161,19 @@ pub async fn publish_newsletter(
         }
     };
 
-    let issue_id = insert_newsletter_issue(&mut transaction, &title, &text_content, &html_content)
-        .await
-        .context("Failed to store newsletter issue details")?;
+    let issue_id = insert_newsletter_issue(
+        &mut transaction,
+        &title,
+        &text_content,
+        &html_content,
+        send_at.as_ref(),
+        track_opens,
+        track_clicks,
+    )
+    .await
+    .context("Failed to store newsletter issue details")?;
 
-    enqueue_delivery_tasks(&mut transaction, issue_id)
-        .await
-        .context("Failed to enqueue delivery tasks")?;
+    let flash_message = send_or_schedule(&mut transaction, issue_id, send_at.as_ref()).await?;
 
     //let subscribers = get_confirmed_subscribers(&pool).await?;
     //for subscriber in subscribers {
@@ -156,10 +197,7 @@ pub async fn publish_newsletter(
     //}
 
     let response = (
-        signed_jar.add(Cookie::new(
-            "_flash",
-            "The newsletter issue has been accepted - emails will go out shortly.",
-        )),
+        signed_jar.add(Cookie::new("_flash", flash_message)),
         Redirect::to("/admin/newsletter"),
     )
         .into_response();
@@ -168,14 +206,77 @@ pub async fn publish_newsletter(
     Ok(response)
 }
 
+/// Work out the HTML and text versions of an issue: explicit content wins
+/// over what is rendered from the Markdown body.
+fn issue_content(
+    markdown_content: Option<String>,
+    html_content: Option<String>,
+    text_content: Option<String>,
+) -> Result<(String, String), String> {
+    let non_empty = |s: Option<String>| s.filter(|s| !s.trim().is_empty());
+    let markdown_content = non_empty(markdown_content);
+    let html_content =
+        non_empty(html_content).or_else(|| markdown_content.as_deref().map(markdown::render_html));
+    let text_content =
+        non_empty(text_content).or_else(|| markdown_content.as_deref().map(markdown::render_text));
+    match (html_content, text_content) {
+        (Some(html_content), Some(text_content)) => Ok((html_content, text_content)),
+        _ => Err("Provide a Markdown body, or both HTML and text content.".into()),
+    }
+}
+
+/// Parse the HTML and text content of an issue, rejecting merge tags that
+/// could not be rendered at send time.
+pub(crate) fn parse_templates(
+    html_content: &str,
+    text_content: &str,
+) -> Result<(IssueTemplate, IssueTemplate), String> {
+    let html_template = IssueTemplate::parse(html_content)
+        .map_err(|e| format!("The HTML content is invalid: {}", e))?;
+    let text_template = IssueTemplate::parse(text_content)
+        .map_err(|e| format!("The text content is invalid: {}", e))?;
+    Ok((html_template, text_template))
+}
+
+/// Hand a freshly published issue over to the delivery workers, unless it
+/// is scheduled for later: the workers pick it up on their own when due.
+/// Returns the message to flash to the admin.
+pub(crate) async fn send_or_schedule(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+    send_at: Option<&SendAt>,
+) -> Result<String, anyhow::Error> {
+    let Some(send_at) = send_at else {
+        enqueue_delivery_tasks(transaction, issue_id)
+            .await
+            .context("Failed to enqueue delivery tasks")?;
+        notify_workers(transaction)
+            .await
+            .context("Failed to notify the delivery workers")?;
+        return Ok(
+            "The newsletter issue has been accepted - emails will go out shortly.".to_string(),
+        );
+    };
+    Ok(format!(
+        r#"The newsletter issue has been scheduled for {}. <a href="/admin/issues/{}">Manage it</a>."#,
+        send_at, issue_id
+    ))
+}
+
+/// Store a new issue: published right away, or held back until `send_at`.
 #[tracing::instrument(skip_all)]
 async fn insert_newsletter_issue(
     transaction: &mut Transaction<'_, Postgres>,
     title: &str,
     text_content: &str,
     html_content: &str,
+    send_at: Option<&SendAt>,
+    track_opens: bool,
+    track_clicks: bool,
 ) -> Result<Uuid, sqlx::Error> {
     let newsletter_issue_id = Uuid::new_v4();
+    let scheduled_for = send_at.map(|send_at| *send_at.as_ref());
+    let published_at = send_at.is_none().then(Utc::now);
     sqlx::query!(
         r#"
         INSERT INTO newsletter_issues (
@@ -183,39 +284,29 @@ async fn insert_newsletter_issue(
             title,
             text_content,
             html_content,
-            published_at
+            published_at,
+            status,
+            scheduled_for,
+            track_opens,
+            track_clicks
+        )
+        VALUES (
+            $1, $2, $3, $4, $5,
+            CASE WHEN $6::timestamptz IS NULL THEN 'published' ELSE 'scheduled' END,
+            $6, $7, $8
         )
-        VALUES ($1, $2, $3, $4, now())
         "#,
         newsletter_issue_id,
         title,
         text_content,
-        html_content
+        html_content,
+        published_at,
+        scheduled_for,
+        track_opens,
+        track_clicks
     )
     .execute(transaction)
     .await?;
 
     Ok(newsletter_issue_id)
 }
-
-#[tracing::instrument(skip_all)]
-async fn enqueue_delivery_tasks(
-    transaction: &mut Transaction<'_, Postgres>,
-    newsletter_issue_id: Uuid,
-) -> Result<(), sqlx::Error> {
-    sqlx::query!(
-        r#"
-        INSERT INTO issue_delivery_queue (
-            newsletter_issue_id,
-            subscriber_email
-        )
-        SELECT $1, email
-        FROM subscriptions
-        WHERE status = 'confirmed'
-        "#,
-        newsletter_issue_id,
-    )
-    .execute(transaction)
-    .await?;
-    Ok(())
-}
@@ -0,0 +1,347 @@
+use anyhow::Context;
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Redirect},
+    Form,
+};
+use axum_extra::extract::SignedCookieJar;
+use cookie::Cookie;
+use htmlescape::encode_minimal;
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+
+use super::SubscribersError;
+use crate::{
+    domain::{NewSubscriber, SubscriberEmail, SubscriberName},
+    email_client::EmailClient,
+    routes::{generate_subscription_token, send_confirmation_email, store_token},
+    startup::ApplicationBaseUrl,
+};
+
+#[derive(serde::Deserialize)]
+pub struct EditFormData {
+    name: String,
+    email: String,
+}
+
+struct Subscriber {
+    name: String,
+    email: String,
+    status: String,
+}
+
+/// Redirect to the detail view of the subscriber, with `message` flashed.
+fn back_to_subscriber(
+    signed_jar: SignedCookieJar,
+    subscriber_id: Uuid,
+    message: impl Into<String>,
+) -> axum::response::Response {
+    (
+        signed_jar.add(
+            Cookie::build("_flash", message.into())
+                .path("/admin")
+                .finish(),
+        ),
+        Redirect::to(&format!("/admin/subscribers/{}", subscriber_id)),
+    )
+        .into_response()
+}
+
+/// Confirm a subscriber on their behalf, e.g. when the confirmation email
+/// never reached them. Also lifts a suspension.
+#[tracing::instrument(skip(pool, signed_jar), err(Debug))]
+pub async fn confirm_subscriber_manually(
+    State(pool): State<sqlx::PgPool>,
+    signed_jar: SignedCookieJar,
+    Path(subscriber_id): Path<Uuid>,
+) -> Result<axum::response::Response, SubscribersError> {
+    let mut transaction = begin(&pool).await?;
+    let Some(subscriber) = lock_subscriber(&mut transaction, subscriber_id).await? else {
+        return Ok(axum::http::StatusCode::NOT_FOUND.into_response());
+    };
+    if !["pending_confirmation", "suspended"].contains(&subscriber.status.as_str()) {
+        return Ok(back_to_subscriber(
+            signed_jar,
+            subscriber_id,
+            "Only subscribers pending confirmation or suspended can be confirmed.",
+        ));
+    }
+    set_status(&mut transaction, subscriber_id, "confirmed").await?;
+    commit(transaction).await?;
+    Ok(back_to_subscriber(
+        signed_jar,
+        subscriber_id,
+        "The subscriber has been confirmed.",
+    ))
+}
+
+#[tracing::instrument(skip(pool, email_client, base_url, signed_jar), err(Debug))]
+pub async fn resend_confirmation(
+    State(pool): State<sqlx::PgPool>,
+    State(email_client): State<EmailClient>,
+    State(base_url): State<ApplicationBaseUrl>,
+    signed_jar: SignedCookieJar,
+    Path(subscriber_id): Path<Uuid>,
+) -> Result<axum::response::Response, SubscribersError> {
+    let mut transaction = begin(&pool).await?;
+    let Some(subscriber) = lock_subscriber(&mut transaction, subscriber_id).await? else {
+        return Ok(axum::http::StatusCode::NOT_FOUND.into_response());
+    };
+    if subscriber.status != "pending_confirmation" {
+        return Ok(back_to_subscriber(
+            signed_jar,
+            subscriber_id,
+            "Only subscribers pending confirmation can be sent a confirmation email.",
+        ));
+    }
+    let new_subscriber = match SubscriberEmail::parse(subscriber.email)
+        .and_then(|email| Ok((email, SubscriberName::parse(subscriber.name)?)))
+    {
+        Ok((email, name)) => NewSubscriber { email, name },
+        Err(e) => {
+            return Ok(back_to_subscriber(
+                signed_jar,
+                subscriber_id,
+                encode_minimal(&e),
+            ))
+        }
+    };
+
+    let subscription_token = generate_subscription_token();
+    store_token(&mut transaction, subscriber_id, &subscription_token)
+        .await
+        .context("Failed to store the confirmation token.")?;
+    commit(transaction).await?;
+    let message = match send_confirmation_email(
+        &base_url.0,
+        &email_client,
+        new_subscriber,
+        &subscription_token,
+    )
+    .await
+    {
+        Ok(()) => "A new confirmation email has been sent.",
+        Err(e) => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to resend a confirmation email."
+            );
+            "Failed to send the confirmation email."
+        }
+    };
+    Ok(back_to_subscriber(signed_jar, subscriber_id, message))
+}
+
+#[tracing::instrument(skip(pool, signed_jar, form), err(Debug))]
+pub async fn update_subscriber(
+    State(pool): State<sqlx::PgPool>,
+    signed_jar: SignedCookieJar,
+    Path(subscriber_id): Path<Uuid>,
+    Form(form): Form<EditFormData>,
+) -> Result<axum::response::Response, SubscribersError> {
+    let (name, email) = match SubscriberName::parse(form.name)
+        .and_then(|name| Ok((name, SubscriberEmail::parse(form.email)?)))
+    {
+        Ok(details) => details,
+        Err(e) => {
+            return Ok(back_to_subscriber(
+                signed_jar,
+                subscriber_id,
+                encode_minimal(&e),
+            ))
+        }
+    };
+
+    let mut transaction = begin(&pool).await?;
+    let Some(subscriber) = lock_subscriber(&mut transaction, subscriber_id).await? else {
+        return Ok(axum::http::StatusCode::NOT_FOUND.into_response());
+    };
+    match update_details(&mut transaction, subscriber_id, &subscriber, &name, &email).await {
+        Err(sqlx::Error::Database(e)) if e.constraint() == Some("subscriptions_email_key") => {
+            return Ok(back_to_subscriber(
+                signed_jar,
+                subscriber_id,
+                format!(
+                    "Another subscriber already uses {}.",
+                    encode_minimal(email.as_ref())
+                ),
+            ));
+        }
+        result => result.context("Failed to update the subscriber details.")?,
+    }
+    commit(transaction).await?;
+    Ok(back_to_subscriber(
+        signed_jar,
+        subscriber_id,
+        "The subscriber details have been saved.",
+    ))
+}
+
+/// Stop mailing a subscriber until they are confirmed again.
+#[tracing::instrument(skip(pool, signed_jar), err(Debug))]
+pub async fn suspend_subscriber(
+    State(pool): State<sqlx::PgPool>,
+    signed_jar: SignedCookieJar,
+    Path(subscriber_id): Path<Uuid>,
+) -> Result<axum::response::Response, SubscribersError> {
+    let mut transaction = begin(&pool).await?;
+    let Some(subscriber) = lock_subscriber(&mut transaction, subscriber_id).await? else {
+        return Ok(axum::http::StatusCode::NOT_FOUND.into_response());
+    };
+    if subscriber.status != "confirmed" {
+        return Ok(back_to_subscriber(
+            signed_jar,
+            subscriber_id,
+            "Only confirmed subscribers can be suspended.",
+        ));
+    }
+    set_status(&mut transaction, subscriber_id, "suspended").await?;
+    delete_queued_deliveries(&mut transaction, &subscriber.email).await?;
+    commit(transaction).await?;
+    Ok(back_to_subscriber(
+        signed_jar,
+        subscriber_id,
+        "The subscriber has been suspended.",
+    ))
+}
+
+/// Delete a subscriber, along with their confirmation tokens and the
+/// deliveries still queued for them.
+#[tracing::instrument(skip(pool, signed_jar), err(Debug))]
+pub async fn delete_subscriber(
+    State(pool): State<sqlx::PgPool>,
+    signed_jar: SignedCookieJar,
+    Path(subscriber_id): Path<Uuid>,
+) -> Result<axum::response::Response, SubscribersError> {
+    let mut transaction = begin(&pool).await?;
+    let Some(subscriber) = lock_subscriber(&mut transaction, subscriber_id).await? else {
+        return Ok(axum::http::StatusCode::NOT_FOUND.into_response());
+    };
+    delete_queued_deliveries(&mut transaction, &subscriber.email).await?;
+    delete(&mut transaction, subscriber_id).await?;
+    commit(transaction).await?;
+    Ok((
+        signed_jar.add(
+            Cookie::build(
+                "_flash",
+                format!("{} has been deleted.", encode_minimal(&subscriber.email)),
+            )
+            .path("/admin")
+            .finish(),
+        ),
+        Redirect::to("/admin/subscribers"),
+    )
+        .into_response())
+}
+
+async fn begin(pool: &sqlx::PgPool) -> Result<Transaction<'static, Postgres>, anyhow::Error> {
+    pool.begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+}
+
+async fn commit(transaction: Transaction<'static, Postgres>) -> Result<(), anyhow::Error> {
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to update a subscriber.")
+}
+
+#[tracing::instrument(skip(transaction))]
+async fn lock_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<Option<Subscriber>, anyhow::Error> {
+    let subscriber = sqlx::query_as!(
+        Subscriber,
+        r#"SELECT name, email, status FROM subscriptions WHERE id = $1 FOR UPDATE"#,
+        subscriber_id
+    )
+    .fetch_optional(transaction)
+    .await
+    .context("Failed to retrieve the subscriber.")?;
+    Ok(subscriber)
+}
+
+#[tracing::instrument(skip(transaction))]
+async fn set_status(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    status: &str,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"UPDATE subscriptions SET status = $2 WHERE id = $1"#,
+        subscriber_id,
+        status
+    )
+    .execute(transaction)
+    .await
+    .context("Failed to update the subscriber status.")?;
+    Ok(())
+}
+
+/// Deliveries already queued for the old address follow it to the new one.
+#[tracing::instrument(skip(transaction, subscriber, name, email))]
+async fn update_details(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    subscriber: &Subscriber,
+    name: &SubscriberName,
+    email: &SubscriberEmail,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE subscriptions SET name = $2, email = $3 WHERE id = $1"#,
+        subscriber_id,
+        name.as_ref(),
+        email.as_ref()
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query!(
+        r#"
+        UPDATE issue_delivery_queue
+        SET subscriber_email = $2
+        WHERE subscriber_email = $1
+        "#,
+        subscriber.email,
+        email.as_ref()
+    )
+    .execute(&mut *transaction)
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(transaction))]
+async fn delete_queued_deliveries(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &str,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"DELETE FROM issue_delivery_queue WHERE subscriber_email = $1"#,
+        email
+    )
+    .execute(transaction)
+    .await
+    .context("Failed to delete the queued deliveries of the subscriber.")?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(transaction))]
+async fn delete(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"DELETE FROM subscription_tokens WHERE subscriber_id = $1"#,
+        subscriber_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to delete the subscription tokens.")?;
+    sqlx::query!(r#"DELETE FROM subscriptions WHERE id = $1"#, subscriber_id)
+        .execute(&mut *transaction)
+        .await
+        .context("Failed to delete the subscriber.")?;
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use anyhow::Context;
+use axum::{
+    extract::{Path, State},
+    response::{Html, IntoResponse},
+};
+use axum_extra::extract::SignedCookieJar;
+use chrono::{DateTime, Utc};
+use cookie::Cookie;
+use htmlescape::{encode_attribute, encode_minimal};
+use uuid::Uuid;
+
+use super::SubscribersError;
+
+struct Subscriber {
+    name: String,
+    email: String,
+    status: String,
+    subscribed_at: DateTime<Utc>,
+}
+
+struct Delivery {
+    newsletter_issue_id: Uuid,
+    title: String,
+    outcome: String,
+    last_attempted_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(skip(pool, signed_jar), err(Debug))]
+pub async fn subscriber_detail(
+    State(pool): State<sqlx::PgPool>,
+    signed_jar: SignedCookieJar,
+    Path(subscriber_id): Path<Uuid>,
+) -> Result<axum::response::Response, SubscribersError> {
+    let flash_html = match signed_jar.get("_flash") {
+        None => "".into(),
+        Some(cookie) => {
+            format!("<p><i>{}</i></p>", cookie.value())
+        }
+    };
+    let Some(subscriber) = get_subscriber(&pool, subscriber_id).await? else {
+        return Ok(axum::http::StatusCode::NOT_FOUND.into_response());
+    };
+    let deliveries = get_deliveries(&pool, &subscriber.email).await?;
+
+    let delivery_rows = if deliveries.is_empty() {
+        "<tr><td colspan=\"3\">No issue has been sent to this subscriber yet.</td></tr>".to_string()
+    } else {
+        deliveries
+            .iter()
+            .map(|delivery| {
+                format!(
+                    r#"<tr><td><a href="/admin/issues/{}">{}</a></td><td>{}</td><td>{}</td></tr>"#,
+                    delivery.newsletter_issue_id,
+                    encode_minimal(&delivery.title),
+                    delivery.outcome,
+                    delivery.last_attempted_at.format("%Y-%m-%d %H:%M UTC"),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n        ")
+    };
+    let name = encode_minimal(&subscriber.name);
+    let email = encode_minimal(&subscriber.email);
+    let status = &subscriber.status;
+    let actions_html = status_actions(subscriber_id, status);
+    let name_attribute = encode_attribute(&subscriber.name);
+    let email_attribute = encode_attribute(&subscriber.email);
+    let subscribed_at = subscriber.subscribed_at.format("%Y-%m-%d %H:%M UTC");
+
+    let body = Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>{name}</title>
+</head>
+<body>
+    {flash_html}
+    <h1>{name}</h1>
+    <table>
+        <tr><th>Email</th><td>{email}</td></tr>
+        <tr><th>Status</th><td>{status}</td></tr>
+        <tr><th>Subscribed at</th><td>{subscribed_at}</td></tr>
+    </table>
+    {actions_html}
+    <h2>Edit</h2>
+    <form action="/admin/subscribers/{subscriber_id}/edit" method="post">
+        <label>Name
+            <input type="text" name="name" value="{name_attribute}">
+        </label>
+        <label>Email
+            <input type="text" name="email" value="{email_attribute}">
+        </label>
+        <button type="submit">Save</button>
+    </form>
+    <form action="/admin/subscribers/{subscriber_id}/delete" method="post">
+        <button type="submit">Delete permanently</button>
+    </form>
+    <h2>Deliveries</h2>
+    <table>
+        <tr><th>Issue</th><th>Outcome</th><th>Last attempt</th></tr>
+        {delivery_rows}
+    </table>
+    <p><a href="/admin/subscribers">&lt;- Back</a></p>
+</body>
+</html>"#,
+    ));
+    let flash_cookie = Cookie::build("_flash", "").path("/admin").finish();
+    Ok((signed_jar.remove(flash_cookie), body).into_response())
+}
+
+/// The status changes that make sense from `status`.
+fn status_actions(subscriber_id: Uuid, status: &str) -> String {
+    let action = |path: &str, label: &str| {
+        format!(
+            r#"<form action="/admin/subscribers/{}/{}" method="post">
+        <button type="submit">{}</button>
+    </form>"#,
+            subscriber_id, path, label
+        )
+    };
+    match status {
+        "pending_confirmation" => format!(
+            "{}
+    {}",
+            action("confirm", "Confirm"),
+            action("resend_confirmation", "Resend confirmation email")
+        ),
+        "confirmed" => action("suspend", "Suspend"),
+        "suspended" => action("confirm", "Confirm again"),
+        _ => "".into(),
+    }
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_subscriber(
+    pool: &sqlx::PgPool,
+    subscriber_id: Uuid,
+) -> Result<Option<Subscriber>, anyhow::Error> {
+    let subscriber = sqlx::query_as!(
+        Subscriber,
+        r#"
+        SELECT name, email, status, subscribed_at
+        FROM subscriptions
+        WHERE id = $1
+        "#,
+        subscriber_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the subscriber.")?;
+    Ok(subscriber)
+}
+
+/// The most recent issues sent (or attempted) to a subscriber.
+#[tracing::instrument(skip(pool))]
+async fn get_deliveries(pool: &sqlx::PgPool, email: &str) -> Result<Vec<Delivery>, anyhow::Error> {
+    let deliveries = sqlx::query_as!(
+        Delivery,
+        r#"
+        SELECT l.newsletter_issue_id, i.title, l.outcome, l.last_attempted_at
+        FROM issue_delivery_log l
+        JOIN newsletter_issues i USING (newsletter_issue_id)
+        WHERE l.subscriber_email = $1
+        ORDER BY l.last_attempted_at DESC
+        LIMIT 20
+        "#,
+        email
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the deliveries of the subscriber.")?;
+    Ok(deliveries)
+}
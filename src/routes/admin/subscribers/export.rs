@@ -0,0 +1,198 @@
+use std::borrow::Cow;
+
+use anyhow::Context;
+use axum::{
+    body::{Bytes, StreamBody},
+    extract::{Query, State},
+    http::header,
+    response::IntoResponse,
+};
+use chrono::{DateTime, Utc};
+use csv_async::AsyncWriterBuilder;
+use futures_util::StreamExt;
+use tokio::sync::mpsc;
+use tracing::Instrument;
+use uuid::Uuid;
+
+use super::{list::STATUSES, SubscribersError};
+
+/// How many rows are encoded together.
+const CHUNK_SIZE: usize = 500;
+/// How many encoded chunks can wait for a slow client before we stop
+/// reading from the database.
+const BUFFERED_CHUNKS: usize = 4;
+const CSV_HEADER: &str = "id,email,name,status,subscribed_at\n";
+
+#[derive(Clone, Copy, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    /// Newline-delimited JSON: one subscriber object per line.
+    Ndjson,
+}
+
+#[derive(serde::Deserialize)]
+pub struct ExportParameters {
+    #[serde(default)]
+    format: ExportFormat,
+    status: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct SubscriberRecord {
+    id: Uuid,
+    email: String,
+    name: String,
+    status: String,
+    subscribed_at: DateTime<Utc>,
+}
+
+/// Stream every subscriber, oldest first. Rows are read and encoded as the
+/// client downloads them, so the table is never held in memory.
+#[tracing::instrument(skip_all, err(Debug))]
+pub async fn export_subscribers(
+    State(pool): State<sqlx::PgPool>,
+    Query(parameters): Query<ExportParameters>,
+) -> Result<axum::response::Response, SubscribersError> {
+    let status = parameters
+        .status
+        .map(|status| status.trim().to_string())
+        .filter(|status| !status.is_empty());
+    if let Some(status) = &status {
+        if !STATUSES.contains(&status.as_str()) {
+            return Err(SubscribersError::InvalidFilter(format!(
+                "{} is not a known subscription status.",
+                status
+            )));
+        }
+    }
+    let format = parameters.format;
+
+    let (sender, mut receiver) = mpsc::channel(BUFFERED_CHUNKS);
+    tokio::spawn(
+        async move {
+            if let Err(e) = stream_subscribers(&pool, status.as_deref(), format, &sender).await {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to export subscribers."
+                );
+                // Abort the download rather than let it end as if complete.
+                let _ = sender
+                    .send(Err(std::io::Error::new(std::io::ErrorKind::Other, e)))
+                    .await;
+            }
+        }
+        .instrument(tracing::Span::current()),
+    );
+    let body = StreamBody::new(futures_util::stream::poll_fn(move |cx| {
+        receiver.poll_recv(cx)
+    }));
+
+    let (content_type, content_disposition) = match format {
+        ExportFormat::Csv => (
+            "text/csv; charset=utf-8",
+            r#"attachment; filename="subscribers.csv""#,
+        ),
+        ExportFormat::Ndjson => (
+            "application/x-ndjson",
+            r#"attachment; filename="subscribers.ndjson""#,
+        ),
+    };
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CONTENT_DISPOSITION, content_disposition),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+async fn stream_subscribers(
+    pool: &sqlx::PgPool,
+    status: Option<&str>,
+    format: ExportFormat,
+    sender: &mpsc::Sender<Result<Bytes, std::io::Error>>,
+) -> Result<(), anyhow::Error> {
+    if let ExportFormat::Csv = format {
+        if sender.send(Ok(Bytes::from(CSV_HEADER))).await.is_err() {
+            return Ok(());
+        }
+    }
+    let mut chunks = sqlx::query_as!(
+        SubscriberRecord,
+        r#"
+        SELECT id, email, name, status, subscribed_at
+        FROM subscriptions
+        WHERE $1::text IS NULL OR status = $1
+        ORDER BY subscribed_at, id
+        "#,
+        status
+    )
+    .fetch(pool)
+    .chunks(CHUNK_SIZE);
+    while let Some(chunk) = chunks.next().await {
+        let records = chunk
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to retrieve subscribers.")?;
+        let encoded = match format {
+            ExportFormat::Csv => encode_csv(&records).await,
+            ExportFormat::Ndjson => encode_ndjson(&records),
+        }?;
+        // The client went away: stop reading.
+        if sender.send(Ok(Bytes::from(encoded))).await.is_err() {
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+async fn encode_csv(records: &[SubscriberRecord]) -> Result<Vec<u8>, anyhow::Error> {
+    let mut serializer = AsyncWriterBuilder::new()
+        .has_headers(false)
+        .create_serializer(Vec::new());
+    for record in records {
+        let row = (
+            record.id,
+            spreadsheet_safe(&record.email),
+            spreadsheet_safe(&record.name),
+            &record.status,
+            record.subscribed_at,
+        );
+        serializer
+            .serialize(row)
+            .await
+            .context("Failed to encode a subscriber as CSV.")?;
+    }
+    serializer
+        .into_inner()
+        .await
+        .map_err(|e| e.into_error())
+        .context("Failed to encode subscribers as CSV.")
+}
+
+/// Spreadsheets evaluate cells starting with one of these as formulas.
+const FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+/// Subscribers choose their own name: make sure opening the export in a
+/// spreadsheet does not run a formula they slipped in.
+fn spreadsheet_safe(value: &str) -> Cow<'_, str> {
+    if value.starts_with(FORMULA_PREFIXES) {
+        Cow::Owned(format!("'{}", value))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+fn encode_ndjson(records: &[SubscriberRecord]) -> Result<Vec<u8>, anyhow::Error> {
+    let mut encoded = Vec::new();
+    for record in records {
+        serde_json::to_writer(&mut encoded, record)
+            .context("Failed to encode a subscriber as JSON.")?;
+        encoded.push(b'\n');
+    }
+    Ok(encoded)
+}
@@ -0,0 +1,488 @@
+use std::collections::HashSet;
+
+use anyhow::Context;
+use axum::{
+    extract::{multipart::Field, Multipart, State},
+    response::Html,
+};
+use csv_async::{AsyncReaderBuilder, StringRecord, Trim};
+use futures_util::{StreamExt, TryStreamExt};
+use htmlescape::encode_minimal;
+use sqlx::{Postgres, Transaction};
+use tokio_util::io::StreamReader;
+use uuid::Uuid;
+
+use super::SubscribersError;
+use crate::{
+    domain::NewSubscriber,
+    email_client::EmailClient,
+    routes::{generate_subscription_token, send_confirmation_email, FormData},
+    startup::ApplicationBaseUrl,
+};
+
+/// How many rows are inserted per query.
+const BATCH_SIZE: usize = 500;
+/// How many confirmation emails are sent at once.
+const CONFIRMATION_CONCURRENCY: usize = 10;
+/// How many confirmation emails an import sends at most: they go out while
+/// the admin waits for the report.
+const MAX_CONFIRMATIONS: usize = 100;
+/// The statuses an imported subscriber can start in. Addresses that
+/// bounced or complained elsewhere should not be imported at all.
+const IMPORT_STATUSES: [&str; 3] = ["pending_confirmation", "confirmed", "unsubscribed"];
+
+/// Where the fields of a subscriber are, going by the header row.
+#[derive(Debug, PartialEq)]
+struct Columns {
+    name: usize,
+    email: usize,
+    status: Option<usize>,
+}
+
+impl Columns {
+    fn from_headers(headers: &StringRecord) -> Option<Self> {
+        let position = |column: &str| {
+            headers
+                .iter()
+                .position(|header| header.eq_ignore_ascii_case(column))
+        };
+        Some(Self {
+            name: position("name")?,
+            email: position("email")?,
+            status: position("status"),
+        })
+    }
+}
+
+struct ImportRow {
+    line: u64,
+    id: Uuid,
+    subscriber: NewSubscriber,
+    status: String,
+}
+
+struct RowError {
+    line: u64,
+    email: String,
+    error: String,
+}
+
+#[derive(Default)]
+struct ImportReport {
+    n_imported: usize,
+    n_confirmations_sent: usize,
+    /// Subscribers left pending confirmation without an email, past
+    /// `MAX_CONFIRMATIONS`.
+    n_confirmations_skipped: usize,
+    errors: Vec<RowError>,
+}
+
+impl ImportReport {
+    fn reject(&mut self, line: u64, email: impl Into<String>, error: impl Into<String>) {
+        self.errors.push(RowError {
+            line,
+            email: email.into(),
+            error: error.into(),
+        });
+    }
+}
+
+pub async fn import_subscribers_form() -> Html<&'static str> {
+    Html(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Import subscribers</title>
+</head>
+<body>
+    <p>Upload a CSV file with <code>name</code> and <code>email</code> columns,
+    and optionally a <code>status</code> column (<code>pending_confirmation</code>,
+    <code>confirmed</code> or <code>unsubscribed</code>; subscribers are pending
+    confirmation by default).</p>
+    <form action="/admin/subscribers/import" method="post" enctype="multipart/form-data">
+        <label>
+            <input type="checkbox" name="send_confirmations" value="true">
+            Send a confirmation email to subscribers pending confirmation
+            (the first 100 of them)
+        </label>
+        <br>
+        <label>CSV file
+            <input type="file" name="file" accept=".csv,text/csv">
+        </label>
+        <br>
+        <button type="submit">Import</button>
+    </form>
+    <p><a href="/admin/subscribers">&lt;- Back</a></p>
+</body>
+</html>"#,
+    )
+}
+
+/// Import the subscribers of an uploaded CSV file. Invalid rows are
+/// reported and skipped; valid ones are inserted in a single transaction, so
+/// that an upload failing halfway through imports nothing.
+#[tracing::instrument(skip_all, err(Debug))]
+pub async fn import_subscribers(
+    State(pool): State<sqlx::PgPool>,
+    State(email_client): State<EmailClient>,
+    State(base_url): State<ApplicationBaseUrl>,
+    mut multipart: Multipart,
+) -> Result<Html<String>, SubscribersError> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let mut report = ImportReport::default();
+    let mut pending = None;
+    let mut send_confirmations = false;
+    while let Some(field) = multipart.next_field().await.map_err(invalid_upload)? {
+        match field.name() {
+            // The fields come in the order of the form, which asks whether
+            // to send confirmations first: the rows pending confirmation
+            // are only kept, up to `MAX_CONFIRMATIONS`, if they are.
+            Some("send_confirmations") if pending.is_none() => {
+                send_confirmations = field.text().await.map_err(invalid_upload)? == "true";
+            }
+            Some("send_confirmations") => {
+                return Err(SubscribersError::InvalidImport(
+                    "Whether to send confirmation emails must be set before the CSV file.".into(),
+                ));
+            }
+            Some("file") => {
+                pending = Some(
+                    import_csv(&mut transaction, field, send_confirmations, &mut report).await?,
+                );
+            }
+            _ => {}
+        }
+    }
+    let Some(pending) = pending else {
+        return Err(SubscribersError::InvalidImport(
+            "No CSV file was uploaded.".into(),
+        ));
+    };
+
+    let confirmations = pending
+        .into_iter()
+        .map(|row| (row, generate_subscription_token()))
+        .collect::<Vec<_>>();
+    if !confirmations.is_empty() {
+        store_tokens(&mut transaction, &confirmations)
+            .await
+            .context("Failed to store the confirmation tokens of imported subscribers.")?;
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to import subscribers.")?;
+
+    let n_confirmations = confirmations.len();
+    let base_url = &base_url.0;
+    let email_client = &email_client;
+    let failures = futures_util::stream::iter(confirmations)
+        .map(|(row, subscription_token)| async move {
+            let line = row.line;
+            let email = row.subscriber.email.as_ref().to_string();
+            send_confirmation_email(base_url, email_client, row.subscriber, &subscription_token)
+                .await
+                .map_err(|e| (line, email, e))
+        })
+        .buffer_unordered(CONFIRMATION_CONCURRENCY)
+        .filter_map(|result| async move { result.err() })
+        .collect::<Vec<_>>()
+        .await;
+    report.n_confirmations_sent = n_confirmations - failures.len();
+    for (line, email, e) in failures {
+        tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to send a confirmation email to an imported subscriber."
+        );
+        report.reject(line, email, "The confirmation email could not be sent.");
+    }
+
+    report.errors.sort_by_key(|error| error.line);
+    Ok(Html(report_page(&report)))
+}
+
+fn invalid_upload(e: axum::extract::multipart::MultipartError) -> SubscribersError {
+    SubscribersError::InvalidImport(format!("The upload could not be read: {}", e))
+}
+
+/// Stream the rows of the CSV file into the database, batch by batch.
+/// Returns the rows imported pending confirmation that are to be sent a
+/// confirmation email.
+async fn import_csv(
+    transaction: &mut Transaction<'_, Postgres>,
+    field: Field<'_>,
+    send_confirmations: bool,
+    report: &mut ImportReport,
+) -> Result<Vec<ImportRow>, SubscribersError> {
+    let reader =
+        StreamReader::new(field.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e)));
+    let mut csv = AsyncReaderBuilder::new()
+        .trim(Trim::All)
+        .flexible(true)
+        .create_reader(reader);
+    let headers = csv.headers().await.map_err(invalid_csv)?;
+    let columns = Columns::from_headers(headers).ok_or_else(|| {
+        SubscribersError::InvalidImport(
+            "The CSV file must have a header row with name and email columns.".into(),
+        )
+    })?;
+
+    let mut pending = Vec::new();
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    let mut record = StringRecord::new();
+    loop {
+        match csv.read_record(&mut record).await {
+            Ok(false) => break,
+            Ok(true) => {
+                let line = record.position().map_or(0, |position| position.line());
+                match parse_row(&record, &columns) {
+                    Ok((subscriber, status)) => batch.push(ImportRow {
+                        line,
+                        id: Uuid::new_v4(),
+                        subscriber,
+                        status,
+                    }),
+                    Err(error) => {
+                        report.reject(line, record.get(columns.email).unwrap_or_default(), error)
+                    }
+                }
+            }
+            Err(e) if e.is_io_error() => return Err(invalid_csv(e)),
+            // Rows that are not valid UTF-8, for instance, can be skipped.
+            Err(e) => report.reject(
+                e.position().map_or(0, |position| position.line()),
+                "",
+                e.to_string(),
+            ),
+        }
+        if batch.len() == BATCH_SIZE {
+            insert_batch(
+                transaction,
+                std::mem::take(&mut batch),
+                report,
+                send_confirmations.then_some(&mut pending),
+            )
+            .await
+            .context("Failed to insert a batch of imported subscribers.")?;
+        }
+    }
+    insert_batch(
+        transaction,
+        batch,
+        report,
+        send_confirmations.then_some(&mut pending),
+    )
+    .await
+    .context("Failed to insert a batch of imported subscribers.")?;
+    Ok(pending)
+}
+
+fn invalid_csv(e: csv_async::Error) -> SubscribersError {
+    SubscribersError::InvalidImport(format!("The CSV file could not be read: {}", e))
+}
+
+/// Validate a row the same way as a subscription form.
+fn parse_row(record: &StringRecord, columns: &Columns) -> Result<(NewSubscriber, String), String> {
+    let field = |index: usize| record.get(index).unwrap_or_default().to_string();
+    let status = match columns
+        .status
+        .map(field)
+        .filter(|status| !status.is_empty())
+    {
+        None => "pending_confirmation".to_string(),
+        Some(status) if IMPORT_STATUSES.contains(&status.as_str()) => status,
+        Some(status) => {
+            return Err(format!(
+                "{} is not a status subscribers can be imported with.",
+                status
+            ))
+        }
+    };
+    let subscriber = NewSubscriber::try_from(FormData {
+        email: field(columns.email),
+        name: field(columns.name),
+    })?;
+    Ok((subscriber, status))
+}
+
+/// Addresses already on the list, or repeated in the file, are reported
+/// rather than imported twice.
+/// Rows imported pending confirmation are added to `pending`, if given, up
+/// to `MAX_CONFIRMATIONS`.
+#[tracing::instrument(skip_all, fields(batch_size = batch.len()))]
+async fn insert_batch(
+    transaction: &mut Transaction<'_, Postgres>,
+    batch: Vec<ImportRow>,
+    report: &mut ImportReport,
+    mut pending: Option<&mut Vec<ImportRow>>,
+) -> Result<(), sqlx::Error> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+    let mut ids = Vec::with_capacity(batch.len());
+    let mut emails = Vec::with_capacity(batch.len());
+    let mut names = Vec::with_capacity(batch.len());
+    let mut statuses = Vec::with_capacity(batch.len());
+    for row in &batch {
+        ids.push(row.id);
+        emails.push(row.subscriber.email.as_ref().to_string());
+        names.push(row.subscriber.name.as_ref().to_string());
+        statuses.push(row.status.clone());
+    }
+    let inserted = sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+        SELECT id, email, name, now(), status
+        FROM UNNEST($1::uuid[], $2::text[], $3::text[], $4::text[])
+            AS imported(id, email, name, status)
+        ON CONFLICT (email) DO NOTHING
+        RETURNING id
+        "#,
+        &ids,
+        &emails,
+        &names,
+        &statuses
+    )
+    .fetch_all(&mut *transaction)
+    .await?
+    .into_iter()
+    .map(|r| r.id)
+    .collect::<HashSet<_>>();
+
+    for row in batch {
+        if !inserted.contains(&row.id) {
+            report.reject(
+                row.line,
+                row.subscriber.email.as_ref(),
+                "This address is already subscribed.",
+            );
+            continue;
+        }
+        report.n_imported += 1;
+        if row.status != "pending_confirmation" {
+            continue;
+        }
+        match pending.as_deref_mut() {
+            Some(pending) if pending.len() < MAX_CONFIRMATIONS => pending.push(row),
+            Some(_) => report.n_confirmations_skipped += 1,
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn store_tokens(
+    transaction: &mut Transaction<'_, Postgres>,
+    confirmations: &[(ImportRow, String)],
+) -> Result<(), sqlx::Error> {
+    let (subscriber_ids, tokens): (Vec<_>, Vec<_>) = confirmations
+        .iter()
+        .map(|(row, token)| (row.id, token.clone()))
+        .unzip();
+    sqlx::query!(
+        r#"
+        INSERT INTO subscription_tokens (subscription_token, subscriber_id)
+        SELECT * FROM UNNEST($1::text[], $2::uuid[])
+        "#,
+        &tokens,
+        &subscriber_ids
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
+fn report_page(report: &ImportReport) -> String {
+    let skipped_confirmations = if report.n_confirmations_skipped > 0 {
+        format!(
+            "\n    <p>At most {} confirmation emails are sent per import: {} subscribers \
+            are pending confirmation without one. They get it if they subscribe again.</p>",
+            MAX_CONFIRMATIONS, report.n_confirmations_skipped
+        )
+    } else {
+        String::new()
+    };
+    let error_rows = if report.errors.is_empty() {
+        "<tr><td colspan=\"3\">Every row was imported.</td></tr>".to_string()
+    } else {
+        report
+            .errors
+            .iter()
+            .map(|error| {
+                format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    error.line,
+                    encode_minimal(&error.email),
+                    encode_minimal(&error.error),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n        ")
+    };
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Import report</title>
+</head>
+<body>
+    <p>{n_imported} subscribers imported, {n_confirmations_sent} confirmation emails sent.</p>{skipped_confirmations}
+    <table>
+        <tr><th>Line</th><th>Email</th><th>Error</th></tr>
+        {error_rows}
+    </table>
+    <p><a href="/admin/subscribers">&lt;- Back</a></p>
+</body>
+</html>"#,
+        n_imported = report.n_imported,
+        n_confirmations_sent = report.n_confirmations_sent,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Columns;
+    use claims::{assert_none, assert_some_eq};
+    use csv_async::StringRecord;
+
+    #[test]
+    fn columns_are_found_whatever_their_order_and_case() {
+        let headers = StringRecord::from(vec!["Email", "status", "NAME"]);
+        assert_some_eq!(
+            Columns::from_headers(&headers),
+            Columns {
+                name: 2,
+                email: 0,
+                status: Some(1)
+            }
+        );
+    }
+
+    #[test]
+    fn the_status_column_is_optional() {
+        let headers = StringRecord::from(vec!["name", "email"]);
+        assert_some_eq!(
+            Columns::from_headers(&headers),
+            Columns {
+                name: 0,
+                email: 1,
+                status: None
+            }
+        );
+    }
+
+    #[test]
+    fn name_and_email_columns_are_required() {
+        assert_none!(Columns::from_headers(&StringRecord::from(vec![
+            "name", "status"
+        ])));
+        assert_none!(Columns::from_headers(&StringRecord::from(vec!["email"])));
+    }
+}
@@ -0,0 +1,306 @@
+use anyhow::Context;
+use axum::{
+    extract::{Query, State},
+    response::{Html, IntoResponse},
+    Json,
+};
+use axum_extra::extract::SignedCookieJar;
+use chrono::{DateTime, SecondsFormat, Utc};
+use cookie::Cookie;
+use htmlescape::{encode_attribute, encode_minimal};
+use uuid::Uuid;
+
+use super::SubscribersError;
+
+/// How many subscribers are listed per page.
+const PAGE_SIZE: usize = 50;
+/// Every status a subscription can be in, for the status filter.
+pub(super) const STATUSES: [&str; 6] = [
+    "pending_confirmation",
+    "confirmed",
+    "unsubscribed",
+    "bounced",
+    "complained",
+    "suspended",
+];
+
+#[derive(serde::Deserialize)]
+pub struct DirectoryParameters {
+    /// Part of a name or email address.
+    q: Option<String>,
+    status: Option<String>,
+    /// The cursor of the previous page, to get the next one.
+    after: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct SubscriberSummary {
+    id: Uuid,
+    name: String,
+    email: String,
+    status: String,
+    subscribed_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+pub struct SubscriberPage {
+    subscribers: Vec<SubscriberSummary>,
+    /// `None` on the last page.
+    next_cursor: Option<String>,
+}
+
+struct Filters {
+    search: Option<String>,
+    status: Option<String>,
+    after: Option<(DateTime<Utc>, Uuid)>,
+}
+
+impl TryFrom<&DirectoryParameters> for Filters {
+    type Error = SubscribersError;
+
+    fn try_from(parameters: &DirectoryParameters) -> Result<Self, Self::Error> {
+        // Empty form fields mean "no filter".
+        let non_empty = |s: &Option<String>| {
+            s.as_deref()
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+        };
+        let status = non_empty(&parameters.status);
+        if let Some(status) = &status {
+            if !STATUSES.contains(&status.as_str()) {
+                return Err(SubscribersError::InvalidFilter(format!(
+                    "{} is not a known subscription status.",
+                    status
+                )));
+            }
+        }
+        let after = non_empty(&parameters.after)
+            .map(|cursor| {
+                parse_cursor(&cursor).ok_or_else(|| {
+                    SubscribersError::InvalidFilter("The page cursor is invalid.".into())
+                })
+            })
+            .transpose()?;
+        Ok(Self {
+            search: non_empty(&parameters.q),
+            status,
+            after,
+        })
+    }
+}
+
+/// Subscribers are listed newest first: a cursor is the position of the
+/// last subscriber of a page in that order.
+fn cursor(subscriber: &SubscriberSummary) -> String {
+    format!(
+        "{}_{}",
+        subscriber
+            .subscribed_at
+            .to_rfc3339_opts(SecondsFormat::Micros, true),
+        subscriber.id
+    )
+}
+
+fn parse_cursor(cursor: &str) -> Option<(DateTime<Utc>, Uuid)> {
+    let (subscribed_at, id) = cursor.split_once('_')?;
+    let subscribed_at = DateTime::parse_from_rfc3339(subscribed_at).ok()?;
+    Some((subscribed_at.with_timezone(&Utc), Uuid::parse_str(id).ok()?))
+}
+
+#[tracing::instrument(skip_all, err(Debug))]
+pub async fn list_subscribers(
+    State(pool): State<sqlx::PgPool>,
+    signed_jar: SignedCookieJar,
+    Query(parameters): Query<DirectoryParameters>,
+) -> Result<axum::response::Response, SubscribersError> {
+    let flash_html = match signed_jar.get("_flash") {
+        None => "".into(),
+        Some(cookie) => {
+            format!("<p><i>{}</i></p>", cookie.value())
+        }
+    };
+    let filters = Filters::try_from(&parameters)?;
+    let page = get_subscriber_page(&pool, &filters).await?;
+
+    let rows = if page.subscribers.is_empty() {
+        "<tr><td colspan=\"4\">No subscribers found.</td></tr>".to_string()
+    } else {
+        page.subscribers
+            .iter()
+            .map(|subscriber| {
+                format!(
+                    r#"<tr><td><a href="/admin/subscribers/{}">{}</a></td><td>{}</td><td>{}</td><td>{}</td></tr>"#,
+                    subscriber.id,
+                    encode_minimal(&subscriber.name),
+                    encode_minimal(&subscriber.email),
+                    subscriber.status,
+                    subscriber.subscribed_at.format("%Y-%m-%d %H:%M UTC"),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n        ")
+    };
+    let status_options = std::iter::once("")
+        .chain(STATUSES)
+        .map(|status| {
+            let selected = if filters.status.as_deref().unwrap_or_default() == status {
+                " selected"
+            } else {
+                ""
+            };
+            let label = if status.is_empty() {
+                "Any status"
+            } else {
+                status
+            };
+            format!(r#"<option value="{status}"{selected}>{label}</option>"#)
+        })
+        .collect::<Vec<_>>()
+        .join("\n                ");
+    let next_page_html = match &page.next_cursor {
+        Some(next_cursor) => {
+            let query = serde_urlencoded::to_string([
+                ("q", filters.search.as_deref().unwrap_or_default()),
+                ("status", filters.status.as_deref().unwrap_or_default()),
+                ("after", next_cursor),
+            ])
+            .context("Failed to encode the next page query.")?;
+            format!(
+                r#"<p><a href="/admin/subscribers?{}">Next page -&gt;</a></p>"#,
+                encode_attribute(&query)
+            )
+        }
+        None => "".into(),
+    };
+    let search = encode_attribute(filters.search.as_deref().unwrap_or_default());
+
+    let body = Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Subscribers</title>
+</head>
+<body>
+    {flash_html}
+    <form action="/admin/subscribers" method="get">
+        <label>Search
+            <input type="text" name="q" value="{search}" placeholder="Name or email">
+        </label>
+        <label>Status
+            <select name="status">
+                {status_options}
+            </select>
+        </label>
+        <button type="submit">Filter</button>
+    </form>
+    <table>
+        <tr><th>Name</th><th>Email</th><th>Status</th><th>Subscribed at</th></tr>
+        {rows}
+    </table>
+    {next_page_html}
+    <p>
+        <a href="/admin/subscribers/import">Import subscribers from a CSV file</a>
+        | Export as <a href="/admin/subscribers/export?format=csv">CSV</a>
+        or <a href="/admin/subscribers/export?format=ndjson">JSON</a>
+    </p>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+    ));
+    let flash_cookie = Cookie::build("_flash", "").path("/admin").finish();
+    Ok((signed_jar.remove(flash_cookie), body).into_response())
+}
+
+/// The same listing as [`list_subscribers`], for scripts.
+#[tracing::instrument(skip_all, err(Debug))]
+pub async fn list_subscribers_json(
+    State(pool): State<sqlx::PgPool>,
+    Query(parameters): Query<DirectoryParameters>,
+) -> Result<Json<SubscriberPage>, SubscribersError> {
+    let filters = Filters::try_from(&parameters)?;
+    let page = get_subscriber_page(&pool, &filters).await?;
+    Ok(Json(page))
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_subscriber_page(
+    pool: &sqlx::PgPool,
+    filters: &Filters,
+) -> Result<SubscriberPage, anyhow::Error> {
+    // `%` and `_` typed in the search box are matched literally.
+    let pattern = filters.search.as_ref().map(|search| {
+        format!(
+            "%{}%",
+            search
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        )
+    });
+    let (after_subscribed_at, after_id) = filters.after.unzip();
+    // One more row than needed tells whether there is a next page.
+    let mut subscribers = sqlx::query_as!(
+        SubscriberSummary,
+        r#"
+        SELECT id, name, email, status, subscribed_at
+        FROM subscriptions
+        WHERE
+            ($1::text IS NULL OR name ILIKE $1 OR email ILIKE $1)
+            AND ($2::text IS NULL OR status = $2)
+            AND ($3::timestamptz IS NULL OR (subscribed_at, id) < ($3, $4::uuid))
+        ORDER BY subscribed_at DESC, id DESC
+        LIMIT $5
+        "#,
+        pattern,
+        filters.status,
+        after_subscribed_at,
+        after_id,
+        PAGE_SIZE as i64 + 1
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve subscribers.")?;
+
+    let next_cursor = if subscribers.len() > PAGE_SIZE {
+        subscribers.truncate(PAGE_SIZE);
+        subscribers.last().map(cursor)
+    } else {
+        None
+    };
+    Ok(SubscriberPage {
+        subscribers,
+        next_cursor,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cursor, parse_cursor, SubscriberSummary};
+    use claims::{assert_none, assert_some_eq};
+
+    #[test]
+    fn a_cursor_round_trips() {
+        let subscriber = SubscriberSummary {
+            id: uuid::Uuid::new_v4(),
+            name: "Ursula".into(),
+            email: "ursula@example.com".into(),
+            status: "confirmed".into(),
+            subscribed_at: "2023-04-27T11:18:21.123456Z".parse().unwrap(),
+        };
+        assert_some_eq!(
+            parse_cursor(&cursor(&subscriber)),
+            (subscriber.subscribed_at, subscriber.id)
+        );
+    }
+
+    #[test]
+    fn malformed_cursors_are_rejected() {
+        assert_none!(parse_cursor("2023-04-27T11:18:21Z"));
+        assert_none!(parse_cursor(
+            "yesterday_0d6d7c4e-7d93-4bd1-a3a5-0eebd1a7e3a1"
+        ));
+        assert_none!(parse_cursor("2023-04-27T11:18:21Z_not-a-uuid"));
+    }
+}
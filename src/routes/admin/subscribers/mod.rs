@@ -0,0 +1,43 @@
+mod actions;
+mod detail;
+mod export;
+mod import;
+mod list;
+pub use actions::*;
+pub use detail::subscriber_detail;
+pub use export::*;
+pub use import::*;
+pub use list::*;
+
+use axum::response::IntoResponse;
+
+use crate::routes::error_chain_fmt;
+
+#[derive(thiserror::Error)]
+pub enum SubscribersError {
+    #[error("{0}")]
+    InvalidFilter(String),
+    #[error("{0}")]
+    InvalidImport(String),
+    #[error("Something went wrong")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for SubscribersError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl IntoResponse for SubscribersError {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Self::InvalidFilter(_) | Self::InvalidImport(_) => {
+                (axum::http::StatusCode::BAD_REQUEST, format!("{}", self)).into_response()
+            }
+            Self::UnexpectedError(_) => {
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
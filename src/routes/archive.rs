@@ -0,0 +1,186 @@
+use anyhow::Context;
+use axum::{
+    extract::{Path, State},
+    response::{Html, IntoResponse},
+};
+use chrono::{DateTime, Utc};
+use htmlescape::encode_minimal;
+use uuid::Uuid;
+
+use crate::{
+    domain::{IssueTemplate, MergeFields},
+    email_html,
+    routes::error_chain_fmt,
+};
+
+/// What `{{ name }}` renders to on the public web archive.
+const ARCHIVE_READER_NAME: &str = "reader";
+
+#[derive(thiserror::Error)]
+pub enum ArchiveError {
+    #[error("Something went wrong")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for ArchiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl IntoResponse for ArchiveError {
+    fn into_response(self) -> axum::response::Response {
+        axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    }
+}
+
+/// The public page of a published issue, linked from every email.
+pub fn issue_link(base_url: &str, newsletter_issue_id: Uuid) -> String {
+    format!("{}/issues/{}", base_url, newsletter_issue_id)
+}
+
+struct PublishedIssueSummary {
+    newsletter_issue_id: Uuid,
+    title: String,
+    published_at: DateTime<Utc>,
+}
+
+struct PublishedIssue {
+    title: String,
+    html_content: String,
+    published_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(skip(pool), err(Debug))]
+pub async fn list_published_issues(
+    State(pool): State<sqlx::PgPool>,
+) -> Result<axum::response::Response, ArchiveError> {
+    let issues = get_published_issues(&pool).await?;
+    let items = if issues.is_empty() {
+        "<p>No issues have been published yet.</p>".to_string()
+    } else {
+        let items = issues
+            .iter()
+            .map(|issue| {
+                format!(
+                    r#"<li>{} - <a href="/issues/{}">{}</a></li>"#,
+                    issue.published_at.format("%Y-%m-%d"),
+                    issue.newsletter_issue_id,
+                    encode_minimal(&issue.title)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n        ");
+        format!("<ul>\n        {}\n    </ul>", items)
+    };
+
+    Ok(Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Past issues</title>
+    <link rel="alternate" type="application/rss+xml" href="/feed.rss">
+    <link rel="alternate" type="application/atom+xml" href="/feed.atom">
+</head>
+<body>
+    <h1>Past issues</h1>
+    {items}
+</body>
+</html>"#,
+    ))
+    .into_response())
+}
+
+#[tracing::instrument(skip(pool), err(Debug))]
+pub async fn published_issue(
+    State(pool): State<sqlx::PgPool>,
+    Path(newsletter_issue_id): Path<Uuid>,
+) -> Result<axum::response::Response, ArchiveError> {
+    let issue = match get_published_issue(&pool, newsletter_issue_id).await? {
+        Some(issue) => issue,
+        None => return Ok(axum::http::StatusCode::NOT_FOUND.into_response()),
+    };
+    let title = encode_minimal(&issue.title);
+    let published_at = issue.published_at.format("%Y-%m-%d");
+    let content = web_content(&issue.html_content);
+
+    Ok(Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>{title}</title>
+</head>
+<body>
+    <h1>{title}</h1>
+    <p>Published on {published_at}</p>
+    <article>
+    {content}
+    </article>
+    <p><a href="/issues">&lt;- Past issues</a></p>
+</body>
+</html>"#,
+    ))
+    .into_response())
+}
+
+/// The HTML of an issue as shown outside of emails, to readers we know
+/// nothing about.
+pub(super) fn web_content(html_content: &str) -> String {
+    let fields = MergeFields {
+        name: ARCHIVE_READER_NAME,
+        email: "",
+        unsubscribe_url: "/",
+    };
+    let content = IssueTemplate::parse(html_content)
+        .unwrap_or_else(|_| IssueTemplate::literal(html_content))
+        .render_html(&fields);
+    // Issues published before their HTML was sanitised on the way in.
+    email_html::sanitize(&content)
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_published_issues(
+    pool: &sqlx::PgPool,
+) -> Result<Vec<PublishedIssueSummary>, anyhow::Error> {
+    let issues = sqlx::query_as!(
+        PublishedIssueSummary,
+        r#"
+        SELECT
+            newsletter_issue_id,
+            title,
+            published_at AS "published_at!"
+        FROM newsletter_issues
+        WHERE status = 'published'
+        ORDER BY published_at DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the published newsletter issues.")?;
+    Ok(issues)
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_published_issue(
+    pool: &sqlx::PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<Option<PublishedIssue>, anyhow::Error> {
+    let issue = sqlx::query_as!(
+        PublishedIssue,
+        r#"
+        SELECT
+            title,
+            html_content,
+            published_at AS "published_at!"
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1 AND status = 'published'
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the newsletter issue.")?;
+    Ok(issue)
+}
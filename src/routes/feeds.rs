@@ -0,0 +1,207 @@
+use anyhow::Context;
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::IntoResponse,
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use htmlescape::encode_minimal;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use super::archive::{issue_link, web_content, ArchiveError};
+use crate::startup::ApplicationBaseUrl;
+
+const FEED_TITLE: &str = "Newsletter";
+/// Feed readers only care about recent entries.
+const MAX_FEED_ENTRIES: i64 = 20;
+
+struct FeedEntry {
+    newsletter_issue_id: Uuid,
+    title: String,
+    html_content: String,
+    published_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(skip_all, err(Debug))]
+pub async fn rss_feed(
+    State(pool): State<sqlx::PgPool>,
+    State(base_url): State<ApplicationBaseUrl>,
+    headers: HeaderMap,
+) -> Result<axum::response::Response, ArchiveError> {
+    let entries = get_feed_entries(&pool).await?;
+    let base_url = &base_url.0;
+    let last_modified = last_modified(&entries);
+
+    let items = entries
+        .iter()
+        .map(|entry| {
+            let link = issue_link(base_url, entry.newsletter_issue_id);
+            format!(
+                r#"    <item>
+      <title>{title}</title>
+      <link>{link}</link>
+      <guid isPermaLink="true">{link}</guid>
+      <pubDate>{pub_date}</pubDate>
+      <description>{description}</description>
+    </item>
+"#,
+                title = encode_minimal(&entry.title),
+                pub_date = entry.published_at.to_rfc2822(),
+                description = encode_minimal(&web_content(&entry.html_content)),
+            )
+        })
+        .collect::<String>();
+    let body = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom">
+  <channel>
+    <title>{FEED_TITLE}</title>
+    <link>{base_url}/issues</link>
+    <description>Past issues of the {FEED_TITLE}</description>
+    <atom:link href="{base_url}/feed.rss" rel="self" type="application/rss+xml"/>
+    <lastBuildDate>{last_build_date}</lastBuildDate>
+{items}  </channel>
+</rss>
+"#,
+        last_build_date = last_modified.to_rfc2822(),
+    );
+    Ok(feed_response(
+        &headers,
+        "application/rss+xml; charset=utf-8",
+        body,
+        last_modified,
+    ))
+}
+
+#[tracing::instrument(skip_all, err(Debug))]
+pub async fn atom_feed(
+    State(pool): State<sqlx::PgPool>,
+    State(base_url): State<ApplicationBaseUrl>,
+    headers: HeaderMap,
+) -> Result<axum::response::Response, ArchiveError> {
+    let entries = get_feed_entries(&pool).await?;
+    let base_url = &base_url.0;
+    let last_modified = last_modified(&entries);
+
+    let items = entries
+        .iter()
+        .map(|entry| {
+            format!(
+                r#"  <entry>
+    <title>{title}</title>
+    <id>urn:uuid:{id}</id>
+    <link rel="alternate" href="{link}"/>
+    <published>{published}</published>
+    <updated>{published}</updated>
+    <content type="html">{content}</content>
+  </entry>
+"#,
+                title = encode_minimal(&entry.title),
+                id = entry.newsletter_issue_id,
+                link = issue_link(base_url, entry.newsletter_issue_id),
+                published = entry.published_at.to_rfc3339(),
+                content = encode_minimal(&web_content(&entry.html_content)),
+            )
+        })
+        .collect::<String>();
+    let body = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>{FEED_TITLE}</title>
+  <id>{base_url}/issues</id>
+  <link rel="alternate" href="{base_url}/issues"/>
+  <link rel="self" href="{base_url}/feed.atom"/>
+  <updated>{updated}</updated>
+  <author><name>{FEED_TITLE}</name></author>
+{items}</feed>
+"#,
+        updated = last_modified.to_rfc3339(),
+    );
+    Ok(feed_response(
+        &headers,
+        "application/atom+xml; charset=utf-8",
+        body,
+        last_modified,
+    ))
+}
+
+/// When the newest issue was published, or the epoch if there is none yet.
+fn last_modified(entries: &[FeedEntry]) -> DateTime<Utc> {
+    entries
+        .iter()
+        .map(|entry| entry.published_at)
+        .max()
+        .unwrap_or_else(|| {
+            DateTime::from_utc(NaiveDateTime::from_timestamp_opt(0, 0).unwrap(), Utc)
+        })
+}
+
+/// Answer with `304 Not Modified` if the reader already has this version
+/// of the feed, as told by `If-None-Match` or, failing that,
+/// `If-Modified-Since`.
+fn feed_response(
+    request_headers: &HeaderMap,
+    content_type: &'static str,
+    body: String,
+    last_modified: DateTime<Utc>,
+) -> axum::response::Response {
+    let etag = format!("\"{}\"", hex::encode(Sha256::digest(body.as_bytes())));
+    let last_modified = last_modified
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string();
+
+    let not_modified = match request_headers.get(header::IF_NONE_MATCH) {
+        Some(if_none_match) => match if_none_match.to_str() {
+            Ok(tags) => tags
+                .split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag),
+            Err(_) => false,
+        },
+        None => matches!(
+            request_headers
+                .get(header::IF_MODIFIED_SINCE)
+                .and_then(|since| since.to_str().ok())
+                .and_then(|since| DateTime::parse_from_rfc2822(since).ok())
+                .zip(DateTime::parse_from_rfc2822(&last_modified).ok()),
+            Some((since, last_modified)) if last_modified <= since
+        ),
+    };
+
+    let mut response = if not_modified {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        ([(header::CONTENT_TYPE, content_type)], body).into_response()
+    };
+    let headers = response.headers_mut();
+    headers.insert(header::ETAG, HeaderValue::from_str(&etag).unwrap());
+    headers.insert(
+        header::LAST_MODIFIED,
+        HeaderValue::from_str(&last_modified).unwrap(),
+    );
+    response
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_feed_entries(pool: &sqlx::PgPool) -> Result<Vec<FeedEntry>, anyhow::Error> {
+    let entries = sqlx::query_as!(
+        FeedEntry,
+        r#"
+        SELECT
+            newsletter_issue_id,
+            title,
+            html_content,
+            published_at AS "published_at!"
+        FROM newsletter_issues
+        WHERE status = 'published'
+        ORDER BY published_at DESC
+        LIMIT $1
+        "#,
+        MAX_FEED_ENTRIES
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the published newsletter issues.")?;
+    Ok(entries)
+}
@@ -1,14 +1,26 @@
 //pub mod authentication;
 mod admin;
+mod archive;
+mod feeds;
 mod health_check;
 mod home;
 mod login;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_data;
+mod subscriptions_unsubscribe;
+mod tracking;
+mod webhooks;
 
 pub use admin::*;
+pub use archive::*;
+pub use feeds::*;
 pub use health_check::*;
 pub use home::*;
 pub use login::*;
 pub use subscriptions::*;
 pub use subscriptions_confirm::*;
+pub use subscriptions_data::*;
+pub use subscriptions_unsubscribe::*;
+pub use tracking::*;
+pub use webhooks::*;
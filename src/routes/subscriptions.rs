@@ -55,8 +55,8 @@ impl IntoResponse for SubscribeError {
 
 #[derive(Debug, Deserialize)]
 pub struct FormData {
-    email: String,
-    name: String,
+    pub email: String,
+    pub name: String,
 }
 
 impl TryFrom<FormData> for NewSubscriber {
@@ -81,15 +81,42 @@ pub async fn subscribe(
     State(email_client): State<EmailClient>,
     Form(form): Form<FormData>,
 ) -> Result<impl IntoResponse, SubscribeError> {
-    let new_subscriber = form.try_into().map_err(SubscribeError::ValidationError)?;
+    let new_subscriber: NewSubscriber = form.try_into().map_err(SubscribeError::ValidationError)?;
     let mut transaction = pool
         .begin()
         .await
         .context("Failed to acquire a Postgres connection from the pool")?;
 
-    let subscriber_id = insert_subscriber(&mut transaction, &new_subscriber)
+    let inserted_subscriber = insert_subscriber(&mut transaction, &new_subscriber)
         .await
         .context("Failed to insert new subscriber in the database.")?;
+    let subscriber_id = match inserted_subscriber {
+        Some(subscriber_id) => subscriber_id,
+        // The address is already on the list, maybe added by a concurrent
+        // submission: the insert waited for it to commit, so the row can now
+        // be read and locked.
+        None => {
+            let existing_subscriber =
+                get_subscriber_by_email(&mut transaction, &new_subscriber.email)
+                    .await
+                    .context("Failed to look up an existing subscriber by email.")?;
+            match existing_subscriber {
+                // They never clicked the first link, or it expired: send a new one.
+                Some((subscriber_id, status)) if status == "pending_confirmation" => subscriber_id,
+                // Coming back after leaving the list: confirm the address again.
+                Some((subscriber_id, status)) if status == "unsubscribed" => {
+                    mark_subscriber_as_pending(&mut transaction, subscriber_id)
+                        .await
+                        .context("Failed to resubscribe a subscriber.")?;
+                    subscriber_id
+                }
+                // Already confirmed, or an address we must not mail anymore (it
+                // bounced, complained or was suspended): there is nothing to do.
+                // Same if the subscriber was deleted in the meantime.
+                _ => return Ok(axum::http::StatusCode::OK),
+            }
+        }
+    };
 
     let subscription_token = generate_subscription_token();
 
@@ -115,7 +142,7 @@ pub async fn subscribe(
 }
 
 /// Generate a random 25-characters-long case-sensitive subscription token.
-fn generate_subscription_token() -> String {
+pub fn generate_subscription_token() -> String {
     let mut rng = thread_rng();
     std::iter::repeat_with(|| rng.sample(Alphanumeric))
         .map(char::from)
@@ -132,7 +159,7 @@ pub async fn send_confirmation_email(
     email_client: &EmailClient,
     new_subscriber: NewSubscriber,
     subscription_token: &str,
-) -> Result<(), reqwest::Error> {
+) -> Result<(), anyhow::Error> {
     let confirmation_link = format!(
         "{}/subscriptions/confirm?subscription_token={}",
         base_url, subscription_token
@@ -170,6 +197,36 @@ pub async fn store_token(
     Ok(())
 }
 
+#[tracing::instrument(name = "Get an existing subscriber by email", skip(email, transaction))]
+pub async fn get_subscriber_by_email(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    email: &SubscriberEmail,
+) -> Result<Option<(Uuid, String)>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"SELECT id, status FROM subscriptions WHERE email = $1 FOR UPDATE"#,
+        email.as_ref(),
+    )
+    .fetch_optional(transaction)
+    .await?;
+    Ok(row.map(|r| (r.id, r.status)))
+}
+
+#[tracing::instrument(name = "Mark subscriber as pending confirmation", skip(transaction))]
+async fn mark_subscriber_as_pending(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    subscriber_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE subscriptions SET status = 'pending_confirmation' WHERE id = $1"#,
+        subscriber_id,
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
+/// Returns `None`, without inserting anything, if the address is already
+/// on the list.
 #[tracing::instrument(
     name = "Saving new subscriber details in the database",
     skip(new_subscriber, transaction)
@@ -177,19 +234,24 @@ pub async fn store_token(
 pub async fn insert_subscriber(
     transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     new_subscriber: &NewSubscriber,
-) -> Result<Uuid, sqlx::Error> {
+) -> Result<Option<Uuid>, sqlx::Error> {
     let subscriber_id = Uuid::new_v4();
-    sqlx::query!(
-        r#" INSERT INTO subscriptions (id, email, name, subscribed_at, status) VALUES ($1, $2, $3, $4, 'pending_confirmation') "#,
+    let inserted = sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+        VALUES ($1, $2, $3, $4, 'pending_confirmation')
+        ON CONFLICT (email) DO NOTHING
+        RETURNING id
+        "#,
         subscriber_id,
         new_subscriber.email.as_ref(),
         new_subscriber.name.as_ref(),
         Utc::now()
     )
-    .execute(transaction)
+    .fetch_optional(transaction)
     .await?;
     // Using the `?` operator to return early
     // if the function failed, returning a sqlx::Error
     // We will talk about error handling in depth later!
-    Ok(subscriber_id)
+    Ok(inserted.map(|r| r.id))
 }
@@ -2,6 +2,10 @@ use axum::{
     extract::{Query, State},
     response::IntoResponse,
 };
+use chrono::{DateTime, Utc};
+
+/// How long a confirmation link stays valid after being sent out.
+pub const SUBSCRIPTION_TOKEN_TTL_HOURS: i64 = 24;
 
 #[derive(serde::Deserialize)]
 pub struct Parameters {
@@ -12,19 +16,27 @@ pub struct Parameters {
 pub async fn confirm(
     State(pool): State<sqlx::PgPool>,
     parameters: Query<Parameters>,
-) -> impl IntoResponse {
-    let id = match get_subscriber_id_from_token(&pool, &parameters.subscription_token).await {
-        Ok(id) => id,
-        Err(_) => return axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+) -> axum::response::Response {
+    let token = match get_subscription_token(&pool, &parameters.subscription_token).await {
+        Ok(token) => token,
+        Err(_) => return axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response(),
     };
 
-    match id {
-        None => axum::http::StatusCode::UNAUTHORIZED,
-        Some(subscriber_id) => {
-            if confirm_subscriber(&pool, subscriber_id).await.is_err() {
-                return axum::http::StatusCode::INTERNAL_SERVER_ERROR;
+    match token {
+        None => axum::http::StatusCode::UNAUTHORIZED.into_response(),
+        Some(token) if token.is_expired() => (
+            axum::http::StatusCode::GONE,
+            "The confirmation link has expired. Subscribe again to receive a new one.",
+        )
+            .into_response(),
+        Some(token) => {
+            if confirm_subscriber(&pool, token.subscriber_id)
+                .await
+                .is_err()
+            {
+                return axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response();
             }
-            axum::http::StatusCode::OK
+            axum::http::StatusCode::OK.into_response()
         }
     }
 }
@@ -35,7 +47,7 @@ pub async fn confirm_subscriber(
     subscriber_id: uuid::Uuid,
 ) -> Result<(), sqlx::Error> {
     sqlx::query!(
-        r#"UPDATE subscriptions SET status = 'confirmed' WHERE id = $1"#,
+        r#"UPDATE subscriptions SET status = 'confirmed' WHERE id = $1 AND status = 'pending_confirmation'"#,
         subscriber_id,
     )
     .execute(pool)
@@ -47,13 +59,28 @@ pub async fn confirm_subscriber(
     Ok(())
 }
 
-#[tracing::instrument(skip(subscription_token, pool), name = "Get subscriber_id from token")]
-pub async fn get_subscriber_id_from_token(
+pub struct SubscriptionToken {
+    pub subscriber_id: uuid::Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+impl SubscriptionToken {
+    pub fn is_expired(&self) -> bool {
+        Utc::now() - self.created_at > chrono::Duration::hours(SUBSCRIPTION_TOKEN_TTL_HOURS)
+    }
+}
+
+#[tracing::instrument(
+    skip(subscription_token, pool),
+    name = "Get subscription token details"
+)]
+pub async fn get_subscription_token(
     pool: &sqlx::PgPool,
     subscription_token: &str,
-) -> Result<Option<uuid::Uuid>, sqlx::Error> {
-    let result = sqlx::query!(
-        "SELECT subscriber_id FROM subscription_tokens WHERE subscription_token = $1",
+) -> Result<Option<SubscriptionToken>, sqlx::Error> {
+    sqlx::query_as!(
+        SubscriptionToken,
+        "SELECT subscriber_id, created_at FROM subscription_tokens WHERE subscription_token = $1",
         subscription_token,
     )
     .fetch_optional(pool)
@@ -61,6 +88,5 @@ pub async fn get_subscriber_id_from_token(
     .map_err(|e| {
         tracing::error!("Failed to execute query: {:?}", e);
         e
-    })?;
-    Ok(result.map(|r| r.subscriber_id))
+    })
 }
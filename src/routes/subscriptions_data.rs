@@ -0,0 +1,583 @@
+use anyhow::Context;
+use axum::{
+    extract::{Query, State},
+    response::{Html, IntoResponse},
+    Form, Json,
+};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use sqlx::{Postgres, Transaction};
+use tracing::Instrument;
+use uuid::Uuid;
+
+use crate::{
+    domain::SubscriberEmail,
+    email_client::EmailClient,
+    routes::error_chain_fmt,
+    signed_token,
+    startup::{ApplicationBaseUrl, HmacSecret},
+};
+
+const ACCESS_TOKEN_PURPOSE: &str = "data_access";
+const ERASURE_TOKEN_PURPOSE: &str = "data_erasure";
+
+/// Unlike unsubscribe links, these links hand out personal data or destroy
+/// it: a forwarded or leaked email must not keep them working forever.
+fn link_validity() -> Duration {
+    Duration::hours(24)
+}
+
+#[derive(serde::Deserialize)]
+pub struct DataRequestFormData {
+    email: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct DataLinkParameters {
+    token: String,
+}
+
+#[derive(thiserror::Error)]
+pub enum SubscriberDataError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("The link is invalid or has expired.")]
+    InvalidToken(#[source] anyhow::Error),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for SubscriberDataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl IntoResponse for SubscriberDataError {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Self::ValidationError(_) => {
+                (axum::http::StatusCode::BAD_REQUEST, format!("{}", self)).into_response()
+            }
+            Self::InvalidToken(_) => {
+                (axum::http::StatusCode::UNAUTHORIZED, format!("{}", self)).into_response()
+            }
+            Self::UnexpectedError(_) => {
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct SubscriberData {
+    subscription: Subscription,
+    subscription_tokens: Vec<SubscriptionToken>,
+    queued_deliveries: Vec<QueuedDelivery>,
+    deliveries: Vec<Delivery>,
+    failed_deliveries: Vec<FailedDelivery>,
+    opens: Vec<Open>,
+    clicks: Vec<Click>,
+}
+
+#[derive(serde::Serialize)]
+struct Subscription {
+    id: Uuid,
+    email: String,
+    name: String,
+    status: String,
+    subscribed_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+struct SubscriptionToken {
+    subscription_token: String,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+struct QueuedDelivery {
+    newsletter_issue_id: Uuid,
+    n_retries: i32,
+    execute_after: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+struct Delivery {
+    newsletter_issue_id: Uuid,
+    outcome: String,
+    provider_message_id: Option<String>,
+    n_attempts: i32,
+    last_error: Option<String>,
+    first_attempted_at: DateTime<Utc>,
+    last_attempted_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+struct FailedDelivery {
+    newsletter_issue_id: Uuid,
+    n_retries: i32,
+    last_error: String,
+    failed_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+struct Open {
+    newsletter_issue_id: Uuid,
+    opened_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+struct Click {
+    newsletter_issue_id: Uuid,
+    url: String,
+    clicked_at: DateTime<Utc>,
+}
+
+/// Build the link a subscriber can follow to download their data.
+pub fn data_access_link(base_url: &str, hmac_secret: &HmacSecret, subscriber_id: Uuid) -> String {
+    signed_link(
+        base_url,
+        hmac_secret,
+        ACCESS_TOKEN_PURPOSE,
+        "data",
+        subscriber_id,
+    )
+}
+
+/// Build the link a subscriber can follow to have their data erased.
+pub fn data_erasure_link(base_url: &str, hmac_secret: &HmacSecret, subscriber_id: Uuid) -> String {
+    signed_link(
+        base_url,
+        hmac_secret,
+        ERASURE_TOKEN_PURPOSE,
+        "erase",
+        subscriber_id,
+    )
+}
+
+fn signed_link(
+    base_url: &str,
+    hmac_secret: &HmacSecret,
+    purpose: &str,
+    path: &str,
+    subscriber_id: Uuid,
+) -> String {
+    let token = signed_token::sign(
+        hmac_secret,
+        purpose,
+        &format!("{}:{}", subscriber_id, Utc::now().timestamp()),
+    );
+    format!("{}/subscriptions/{}?token={}", base_url, path, token)
+}
+
+fn subscriber_id_from_token(
+    hmac_secret: &HmacSecret,
+    purpose: &str,
+    token: &str,
+) -> Result<Uuid, SubscriberDataError> {
+    signed_token::verify(hmac_secret, purpose, token)
+        .and_then(|payload| parse_payload(&payload, Utc::now()))
+        .map_err(SubscriberDataError::InvalidToken)
+}
+
+fn parse_payload(payload: &str, now: DateTime<Utc>) -> Result<Uuid, anyhow::Error> {
+    let (subscriber_id, issued_at) = payload
+        .split_once(':')
+        .context("The token is missing its issue date.")?;
+    let issued_at = issued_at
+        .parse()
+        .ok()
+        .and_then(|timestamp| NaiveDateTime::from_timestamp_opt(timestamp, 0))
+        .map(|issued_at| DateTime::<Utc>::from_utc(issued_at, Utc))
+        .context("Malformed issue date.")?;
+    if now - issued_at > link_validity() {
+        anyhow::bail!("The link has expired.");
+    }
+    Uuid::parse_str(subscriber_id).context("Malformed subscriber id.")
+}
+
+pub async fn data_request_form() -> Html<&'static str> {
+    Html(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Your data</title>
+</head>
+<body>
+    <p>Enter the address you subscribed with: we will email you a link to
+    download the data we hold about you, and another to have it erased.</p>
+    <form action="/subscriptions/data_request" method="post">
+        <label>Email
+            <input type="email" name="email">
+        </label>
+        <button type="submit">Send me the links</button>
+    </form>
+</body>
+</html>"#,
+    )
+}
+
+// The answer is the same, and takes as long, whether or not the address is
+// on the list: the page must not tell who subscribed. The lookup and the
+// email happen in the background.
+#[tracing::instrument(skip_all, name = "Request data access and erasure links", err(Debug))]
+pub async fn request_subscriber_data(
+    State(pool): State<sqlx::PgPool>,
+    State(email_client): State<EmailClient>,
+    State(base_url): State<ApplicationBaseUrl>,
+    State(hmac_secret): State<HmacSecret>,
+    Form(form): Form<DataRequestFormData>,
+) -> Result<Html<&'static str>, SubscriberDataError> {
+    let email = SubscriberEmail::parse(form.email).map_err(SubscriberDataError::ValidationError)?;
+    tokio::spawn(
+        async move {
+            if let Err(e) =
+                send_data_links(&pool, &email_client, &base_url, &hmac_secret, &email).await
+            {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to send the data access and erasure links."
+                );
+            }
+        }
+        .instrument(tracing::Span::current()),
+    );
+
+    Ok(Html(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Your data</title>
+</head>
+<body>
+    <p>If this address is on our list, you will receive an email with your links shortly.</p>
+</body>
+</html>"#,
+    ))
+}
+
+/// Email the data access and erasure links to `email`, if it is on the list.
+async fn send_data_links(
+    pool: &sqlx::PgPool,
+    email_client: &EmailClient,
+    base_url: &ApplicationBaseUrl,
+    hmac_secret: &HmacSecret,
+    email: &SubscriberEmail,
+) -> Result<(), anyhow::Error> {
+    let subscriber_id = sqlx::query!(
+        r#"SELECT id FROM subscriptions WHERE email = $1"#,
+        email.as_ref()
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to look up a subscriber by email.")?
+    .map(|r| r.id);
+
+    if let Some(subscriber_id) = subscriber_id {
+        let access_link = data_access_link(&base_url.0, hmac_secret, subscriber_id);
+        let erasure_link = data_erasure_link(&base_url.0, hmac_secret, subscriber_id);
+        let plain_body = format!(
+            "You asked about the data we hold about you.\n\
+            Download it from {}\n\
+            Have it erased, and stop receiving our newsletter, from {}\n\
+            Both links are valid for 24 hours. If you did not ask, ignore this email.",
+            access_link, erasure_link
+        );
+        let html_body = format!(
+            "You asked about the data we hold about you.<br/>\
+            <a href=\"{}\">Download it</a>, or \
+            <a href=\"{}\">have it erased</a> and stop receiving our newsletter.<br/>\
+            Both links are valid for 24 hours. If you did not ask, ignore this email.",
+            access_link, erasure_link
+        );
+        email_client
+            .send_email(email, "Your data", &html_body, &plain_body)
+            .await
+            .context("Failed to send the data access and erasure links.")?;
+    }
+    Ok(())
+}
+
+/// Everything stored about a subscriber, as JSON.
+#[tracing::instrument(
+    skip_all,
+    name = "Export the data of a subscriber",
+    fields(subscriber_id = tracing::field::Empty),
+    err(Debug)
+)]
+pub async fn subscriber_data(
+    State(pool): State<sqlx::PgPool>,
+    State(hmac_secret): State<HmacSecret>,
+    parameters: Query<DataLinkParameters>,
+) -> Result<Json<SubscriberData>, SubscriberDataError> {
+    let subscriber_id =
+        subscriber_id_from_token(&hmac_secret, ACCESS_TOKEN_PURPOSE, &parameters.token)?;
+    tracing::Span::current().record("subscriber_id", tracing::field::display(subscriber_id));
+
+    let data = get_subscriber_data(&pool, subscriber_id)
+        .await
+        .context("Failed to retrieve the data of a subscriber.")?
+        .ok_or_else(|| {
+            SubscriberDataError::InvalidToken(anyhow::anyhow!("The subscriber does not exist."))
+        })?;
+    Ok(Json(data))
+}
+
+// Link scanners and mail clients prefetch GET requests: we only ask for a
+// confirmation here, the erasure happens on POST.
+#[tracing::instrument(skip_all, name = "Show the erasure confirmation page")]
+pub async fn erase_subscriber_form(
+    State(hmac_secret): State<HmacSecret>,
+    parameters: Query<DataLinkParameters>,
+) -> Result<impl IntoResponse, SubscriberDataError> {
+    subscriber_id_from_token(&hmac_secret, ERASURE_TOKEN_PURPOSE, &parameters.token)?;
+    let token = &parameters.token;
+    Ok(Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Erase your data</title>
+</head>
+<body>
+    <p>Do you want us to erase your data? You will stop receiving our newsletter.
+    This cannot be undone.</p>
+    <form action="/subscriptions/erase?token={token}" method="post">
+        <button type="submit">Erase my data</button>
+    </form>
+</body>
+</html>"#,
+    )))
+}
+
+#[tracing::instrument(
+    skip_all,
+    name = "Erase a subscriber",
+    fields(subscriber_id = tracing::field::Empty),
+    err(Debug)
+)]
+pub async fn erase_subscriber(
+    State(pool): State<sqlx::PgPool>,
+    State(hmac_secret): State<HmacSecret>,
+    parameters: Query<DataLinkParameters>,
+) -> Result<impl IntoResponse, SubscriberDataError> {
+    let subscriber_id =
+        subscriber_id_from_token(&hmac_secret, ERASURE_TOKEN_PURPOSE, &parameters.token)?;
+    tracing::Span::current().record("subscriber_id", tracing::field::display(subscriber_id));
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let erased = erase(&mut transaction, subscriber_id)
+        .await
+        .context("Failed to erase a subscriber.")?;
+    if !erased {
+        return Err(SubscriberDataError::InvalidToken(anyhow::anyhow!(
+            "The subscriber does not exist."
+        )));
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to erase a subscriber.")?;
+
+    Ok(Html(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Data erased</title>
+</head>
+<body>
+    <p>Your data has been erased. You will not receive any further issues.</p>
+</body>
+</html>"#,
+    ))
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_subscriber_data(
+    pool: &sqlx::PgPool,
+    subscriber_id: Uuid,
+) -> Result<Option<SubscriberData>, sqlx::Error> {
+    let Some(subscription) = sqlx::query_as!(
+        Subscription,
+        r#"SELECT id, email, name, status, subscribed_at FROM subscriptions WHERE id = $1"#,
+        subscriber_id
+    )
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+    let subscription_tokens = sqlx::query_as!(
+        SubscriptionToken,
+        r#"
+        SELECT subscription_token, created_at
+        FROM subscription_tokens
+        WHERE subscriber_id = $1
+        ORDER BY created_at
+        "#,
+        subscriber_id
+    )
+    .fetch_all(pool)
+    .await?;
+    let queued_deliveries = sqlx::query_as!(
+        QueuedDelivery,
+        r#"
+        SELECT newsletter_issue_id, n_retries, execute_after
+        FROM issue_delivery_queue
+        WHERE subscriber_email = $1
+        ORDER BY execute_after
+        "#,
+        subscription.email
+    )
+    .fetch_all(pool)
+    .await?;
+    let deliveries = sqlx::query_as!(
+        Delivery,
+        r#"
+        SELECT newsletter_issue_id, outcome, provider_message_id, n_attempts,
+            last_error, first_attempted_at, last_attempted_at
+        FROM issue_delivery_log
+        WHERE subscriber_email = $1
+        ORDER BY first_attempted_at
+        "#,
+        subscription.email
+    )
+    .fetch_all(pool)
+    .await?;
+    let failed_deliveries = sqlx::query_as!(
+        FailedDelivery,
+        r#"
+        SELECT newsletter_issue_id, n_retries, last_error, failed_at
+        FROM issue_delivery_dead_letters
+        WHERE subscriber_email = $1
+        ORDER BY failed_at
+        "#,
+        subscription.email
+    )
+    .fetch_all(pool)
+    .await?;
+    let opens = sqlx::query_as!(
+        Open,
+        r#"
+        SELECT newsletter_issue_id, opened_at
+        FROM issue_open_events
+        WHERE subscriber_id = $1
+        ORDER BY opened_at
+        "#,
+        subscriber_id
+    )
+    .fetch_all(pool)
+    .await?;
+    let clicks = sqlx::query_as!(
+        Click,
+        r#"
+        SELECT newsletter_issue_id, url, clicked_at
+        FROM issue_click_events
+        WHERE subscriber_id = $1
+        ORDER BY clicked_at
+        "#,
+        subscriber_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(Some(SubscriberData {
+        subscription,
+        subscription_tokens,
+        queued_deliveries,
+        deliveries,
+        failed_deliveries,
+        opens,
+        clicks,
+    }))
+}
+
+/// Delete everything tied to the subscriber. The delivery log is kept for
+/// the issue reports, under a placeholder address and without the details
+/// that could identify them.
+/// Returns `false` if there is no subscriber with the given id.
+#[tracing::instrument(skip(transaction))]
+async fn erase(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let Some(subscriber) = sqlx::query!(
+        r#"SELECT email FROM subscriptions WHERE id = $1 FOR UPDATE"#,
+        subscriber_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    else {
+        return Ok(false);
+    };
+    sqlx::query!(
+        r#"DELETE FROM issue_delivery_queue WHERE subscriber_email = $1"#,
+        subscriber.email
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query!(
+        r#"DELETE FROM issue_delivery_dead_letters WHERE subscriber_email = $1"#,
+        subscriber.email
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query!(
+        r#"
+        UPDATE issue_delivery_log
+        SET subscriber_email = $2, provider_message_id = NULL, last_error = NULL
+        WHERE subscriber_email = $1
+        "#,
+        subscriber.email,
+        format!("erased-{}", Uuid::new_v4())
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query!(
+        r#"DELETE FROM subscription_tokens WHERE subscriber_id = $1"#,
+        subscriber_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    // Open and click events go with the subscription.
+    sqlx::query!(r#"DELETE FROM subscriptions WHERE id = $1"#, subscriber_id)
+        .execute(&mut *transaction)
+        .await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_payload;
+    use chrono::{Duration, Utc};
+    use claims::{assert_err, assert_ok_eq};
+    use uuid::Uuid;
+
+    #[test]
+    fn a_recent_link_is_accepted() {
+        let subscriber_id = Uuid::new_v4();
+        let now = Utc::now();
+        let issued_at = now - Duration::hours(23);
+        let payload = format!("{}:{}", subscriber_id, issued_at.timestamp());
+        assert_ok_eq!(parse_payload(&payload, now), subscriber_id);
+    }
+
+    #[test]
+    fn an_expired_link_is_rejected() {
+        let now = Utc::now();
+        let issued_at = now - Duration::hours(25);
+        let payload = format!("{}:{}", Uuid::new_v4(), issued_at.timestamp());
+        assert_err!(parse_payload(&payload, now));
+    }
+
+    #[test]
+    fn a_payload_without_issue_date_is_rejected() {
+        assert_err!(parse_payload(&Uuid::new_v4().to_string(), Utc::now()));
+    }
+}
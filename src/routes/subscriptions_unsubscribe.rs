@@ -0,0 +1,160 @@
+use anyhow::Context;
+use axum::{
+    extract::{Query, State},
+    response::{Html, IntoResponse},
+};
+use uuid::Uuid;
+
+use crate::{routes::error_chain_fmt, signed_token, startup::HmacSecret};
+
+const UNSUBSCRIBE_TOKEN_PURPOSE: &str = "unsubscribe";
+
+#[derive(serde::Deserialize)]
+pub struct UnsubscribeParameters {
+    token: String,
+}
+
+#[derive(thiserror::Error)]
+pub enum UnsubscribeError {
+    #[error("The unsubscribe link is invalid.")]
+    InvalidToken(#[source] anyhow::Error),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for UnsubscribeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl IntoResponse for UnsubscribeError {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Self::InvalidToken(_) => {
+                (axum::http::StatusCode::UNAUTHORIZED, format!("{}", self)).into_response()
+            }
+            Self::UnexpectedError(_) => {
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
+
+/// Build the link a subscriber can follow to leave the mailing list.
+pub fn unsubscribe_link(base_url: &str, hmac_secret: &HmacSecret, subscriber_id: Uuid) -> String {
+    let token = signed_token::sign(
+        hmac_secret,
+        UNSUBSCRIBE_TOKEN_PURPOSE,
+        &subscriber_id.to_string(),
+    );
+    format!("{}/subscriptions/unsubscribe?token={}", base_url, token)
+}
+
+fn subscriber_id_from_token(
+    hmac_secret: &HmacSecret,
+    token: &str,
+) -> Result<Uuid, UnsubscribeError> {
+    signed_token::verify(hmac_secret, UNSUBSCRIBE_TOKEN_PURPOSE, token)
+        .and_then(|payload| Uuid::parse_str(&payload).context("Malformed subscriber id."))
+        .map_err(UnsubscribeError::InvalidToken)
+}
+
+// Link scanners and mail clients prefetch GET requests: we only ask for a
+// confirmation here, the actual unsubscription happens on POST.
+#[tracing::instrument(skip_all, name = "Show the unsubscribe confirmation page")]
+pub async fn unsubscribe_form(
+    State(hmac_secret): State<HmacSecret>,
+    parameters: Query<UnsubscribeParameters>,
+) -> Result<impl IntoResponse, UnsubscribeError> {
+    subscriber_id_from_token(&hmac_secret, &parameters.token)?;
+    let token = &parameters.token;
+    Ok(Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Unsubscribe</title>
+</head>
+<body>
+    <p>Do you want to stop receiving our newsletter?</p>
+    <form action="/subscriptions/unsubscribe?token={token}" method="post">
+        <button type="submit">Unsubscribe</button>
+    </form>
+</body>
+</html>"#,
+    )))
+}
+
+#[tracing::instrument(
+    skip_all,
+    name = "Unsubscribe a subscriber",
+    fields(subscriber_id = tracing::field::Empty),
+    err(Debug)
+)]
+pub async fn unsubscribe(
+    State(pool): State<sqlx::PgPool>,
+    State(hmac_secret): State<HmacSecret>,
+    parameters: Query<UnsubscribeParameters>,
+) -> Result<impl IntoResponse, UnsubscribeError> {
+    let subscriber_id = subscriber_id_from_token(&hmac_secret, &parameters.token)?;
+    tracing::Span::current().record("subscriber_id", tracing::field::display(subscriber_id));
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let unsubscribed = mark_subscriber_as_unsubscribed(&mut transaction, subscriber_id)
+        .await
+        .context("Failed to update the subscriber status.")?;
+    if !unsubscribed {
+        return Err(UnsubscribeError::InvalidToken(anyhow::anyhow!(
+            "The subscriber does not exist."
+        )));
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to unsubscribe a subscriber.")?;
+
+    Ok(Html(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Unsubscribed</title>
+</head>
+<body>
+    <p>You have been unsubscribed. You will not receive any further issues.</p>
+    <p><a href="/subscriptions/data_request">Download or erase the data we hold about you</a></p>
+</body>
+</html>"#,
+    ))
+}
+
+/// Flag the subscriber as unsubscribed and drop the issues still waiting
+/// to be delivered to them.
+/// Returns `false` if there is no subscriber with the given id.
+#[tracing::instrument(skip(transaction), name = "Mark subscriber as unsubscribed")]
+async fn mark_subscriber_as_unsubscribed(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    subscriber_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let subscriber = sqlx::query!(
+        r#"UPDATE subscriptions SET status = 'unsubscribed' WHERE id = $1 RETURNING email"#,
+        subscriber_id,
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+
+    let Some(subscriber) = subscriber else {
+        return Ok(false);
+    };
+    sqlx::query!(
+        r#"DELETE FROM issue_delivery_queue WHERE subscriber_email = $1"#,
+        subscriber.email,
+    )
+    .execute(&mut *transaction)
+    .await?;
+    Ok(true)
+}
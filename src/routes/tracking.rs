@@ -0,0 +1,188 @@
+use anyhow::Context;
+use axum::{
+    extract::{Path, State},
+    http::header,
+    response::{IntoResponse, Redirect},
+};
+use uuid::Uuid;
+
+use crate::{routes::error_chain_fmt, signed_token, startup::HmacSecret};
+
+const OPEN_TOKEN_PURPOSE: &str = "track_open";
+const CLICK_TOKEN_PURPOSE: &str = "track_click";
+
+/// A transparent 1x1 GIF.
+const PIXEL: &[u8] = &[
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
+
+#[derive(thiserror::Error)]
+pub enum TrackingError {
+    #[error("The tracking link is invalid.")]
+    InvalidToken(#[source] anyhow::Error),
+}
+
+impl std::fmt::Debug for TrackingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl IntoResponse for TrackingError {
+    fn into_response(self) -> axum::response::Response {
+        axum::http::StatusCode::NOT_FOUND.into_response()
+    }
+}
+
+/// The tracking pixel embedded in an issue sent to a subscriber.
+pub fn open_pixel_link(
+    base_url: &str,
+    hmac_secret: &HmacSecret,
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+) -> String {
+    let token = signed_token::sign(
+        hmac_secret,
+        OPEN_TOKEN_PURPOSE,
+        &format!("{}:{}", newsletter_issue_id, subscriber_id),
+    );
+    format!("{}/t/o/{}", base_url, token)
+}
+
+/// A link to `url` that records the click before redirecting to it.
+pub fn click_link(
+    base_url: &str,
+    hmac_secret: &HmacSecret,
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+    url: &str,
+) -> String {
+    let token = signed_token::sign(
+        hmac_secret,
+        CLICK_TOKEN_PURPOSE,
+        &format!("{}:{}:{}", newsletter_issue_id, subscriber_id, url),
+    );
+    format!("{}/t/c/{}", base_url, token)
+}
+
+/// Split a verified token payload into the issue, the subscriber and
+/// whatever follows them.
+fn parse_payload(
+    payload: &str,
+    n_parts: usize,
+) -> Result<(Uuid, Uuid, Option<&str>), anyhow::Error> {
+    let mut parts = payload.splitn(n_parts, ':');
+    let newsletter_issue_id = parts
+        .next()
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .context("Malformed newsletter issue id.")?;
+    let subscriber_id = parts
+        .next()
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .context("Malformed subscriber id.")?;
+    Ok((newsletter_issue_id, subscriber_id, parts.next()))
+}
+
+// Recording an event is best effort: readers get their pixel or their
+// redirect regardless.
+#[tracing::instrument(skip_all, fields(newsletter_issue_id, subscriber_id))]
+pub async fn track_open(
+    State(pool): State<sqlx::PgPool>,
+    State(hmac_secret): State<HmacSecret>,
+    Path(token): Path<String>,
+) -> Result<axum::response::Response, TrackingError> {
+    let payload = signed_token::verify(&hmac_secret, OPEN_TOKEN_PURPOSE, &token)
+        .map_err(TrackingError::InvalidToken)?;
+    let (newsletter_issue_id, subscriber_id, _) =
+        parse_payload(&payload, 2).map_err(TrackingError::InvalidToken)?;
+    record_span_ids(newsletter_issue_id, subscriber_id);
+    if let Err(e) = insert_open_event(&pool, newsletter_issue_id, subscriber_id).await {
+        tracing::error!(error.cause_chain = ?e, error.message = %e, "Failed to record an open.");
+    }
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/gif"),
+            (header::CACHE_CONTROL, "no-store"),
+        ],
+        PIXEL,
+    )
+        .into_response())
+}
+
+#[tracing::instrument(skip_all, fields(newsletter_issue_id, subscriber_id))]
+pub async fn track_click(
+    State(pool): State<sqlx::PgPool>,
+    State(hmac_secret): State<HmacSecret>,
+    Path(token): Path<String>,
+) -> Result<axum::response::Response, TrackingError> {
+    let payload = signed_token::verify(&hmac_secret, CLICK_TOKEN_PURPOSE, &token)
+        .map_err(TrackingError::InvalidToken)?;
+    let (newsletter_issue_id, subscriber_id, url) = parse_payload(&payload, 3)
+        .and_then(|(issue, subscriber, url)| {
+            Ok((issue, subscriber, url.context("Missing link target.")?))
+        })
+        .map_err(TrackingError::InvalidToken)?;
+    record_span_ids(newsletter_issue_id, subscriber_id);
+    if let Err(e) = insert_click_event(&pool, newsletter_issue_id, subscriber_id, url).await {
+        tracing::error!(error.cause_chain = ?e, error.message = %e, "Failed to record a click.");
+    }
+    Ok(Redirect::to(url).into_response())
+}
+
+fn record_span_ids(newsletter_issue_id: Uuid, subscriber_id: Uuid) {
+    let span = tracing::Span::current();
+    span.record(
+        "newsletter_issue_id",
+        tracing::field::display(newsletter_issue_id),
+    );
+    span.record("subscriber_id", tracing::field::display(subscriber_id));
+}
+
+/// Events of subscribers removed since the issue went out are dropped.
+#[tracing::instrument(skip(pool))]
+async fn insert_open_event(
+    pool: &sqlx::PgPool,
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_open_events (newsletter_issue_id, subscriber_id, opened_at)
+        SELECT $1, id, now()
+        FROM subscriptions
+        WHERE id = $2
+        "#,
+        newsletter_issue_id,
+        subscriber_id
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the open event.")?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(pool))]
+async fn insert_click_event(
+    pool: &sqlx::PgPool,
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+    url: &str,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_click_events (newsletter_issue_id, subscriber_id, url, clicked_at)
+        SELECT $1, id, $3, now()
+        FROM subscriptions
+        WHERE id = $2
+        "#,
+        newsletter_issue_id,
+        subscriber_id,
+        url
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the click event.")?;
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use anyhow::Context;
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use secrecy::ExposeSecret;
+use sha2::{Digest, Sha256};
+use sqlx::{Postgres, Transaction};
+
+use crate::{routes::error_chain_fmt, startup::PostmarkWebhookSecret};
+
+const WEBHOOK_SECRET_HEADER: &str = "X-Postmark-Webhook-Secret";
+
+/// The subset of Postmark's webhook payloads we act upon.
+#[derive(serde::Deserialize)]
+#[serde(tag = "RecordType")]
+enum PostmarkEvent {
+    Bounce {
+        #[serde(rename = "Type")]
+        bounce_type: String,
+        #[serde(rename = "Email")]
+        email: String,
+    },
+    SpamComplaint {
+        #[serde(rename = "Email")]
+        email: String,
+    },
+    /// Deliveries, opens, ... are acknowledged and ignored.
+    #[serde(other)]
+    Other,
+}
+
+impl PostmarkEvent {
+    /// The address to stop mailing and its new status, if any.
+    /// Soft bounces (full mailbox, auto-responders, ...) are not final.
+    fn subscriber_update(&self) -> Option<(&str, &'static str)> {
+        match self {
+            Self::Bounce { bounce_type, email }
+                if bounce_type == "HardBounce" || bounce_type == "BadEmailAddress" =>
+            {
+                Some((email, "bounced"))
+            }
+            Self::SpamComplaint { email } => Some((email, "complained")),
+            _ => None,
+        }
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum WebhookError {
+    #[error("The webhook secret is missing or invalid.")]
+    AuthError,
+    #[error("The webhook payload is invalid.")]
+    InvalidPayload(#[source] serde_json::Error),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for WebhookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl IntoResponse for WebhookError {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Self::AuthError => StatusCode::UNAUTHORIZED.into_response(),
+            Self::InvalidPayload(_) => {
+                (StatusCode::BAD_REQUEST, format!("{}", self)).into_response()
+            }
+            Self::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        }
+    }
+}
+
+#[tracing::instrument(name = "Handle a Postmark webhook", skip_all, err(Debug))]
+pub async fn postmark_webhook(
+    State(pool): State<sqlx::PgPool>,
+    State(secret): State<PostmarkWebhookSecret>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, WebhookError> {
+    check_secret(&headers, &secret)?;
+    let event: PostmarkEvent =
+        serde_json::from_slice(&body).map_err(WebhookError::InvalidPayload)?;
+
+    let Some((email, status)) = event.subscriber_update() else {
+        return Ok(StatusCode::OK);
+    };
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    stop_mailing(&mut transaction, email, status)
+        .await
+        .context("Failed to update the subscriber status.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to stop mailing a subscriber.")?;
+    Ok(StatusCode::OK)
+}
+
+fn check_secret(headers: &HeaderMap, secret: &PostmarkWebhookSecret) -> Result<(), WebhookError> {
+    let candidate = headers
+        .get(WEBHOOK_SECRET_HEADER)
+        .ok_or(WebhookError::AuthError)?
+        .as_bytes();
+    // Comparing digests rather than the secrets themselves: how long the
+    // comparison takes tells nothing about the secret.
+    if Sha256::digest(candidate) == Sha256::digest(secret.0.expose_secret().as_bytes()) {
+        Ok(())
+    } else {
+        Err(WebhookError::AuthError)
+    }
+}
+
+/// Flag the subscriber and drop the deliveries still queued for them.
+/// Unknown addresses are ignored.
+#[tracing::instrument(skip(transaction))]
+async fn stop_mailing(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &str,
+    status: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE subscriptions SET status = $2 WHERE email = $1"#,
+        email,
+        status
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query!(
+        r#"DELETE FROM issue_delivery_queue WHERE subscriber_email = $1"#,
+        email
+    )
+    .execute(&mut *transaction)
+    .await?;
+    Ok(())
+}
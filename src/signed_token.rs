@@ -0,0 +1,81 @@
+use anyhow::Context;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use secrecy::ExposeSecret;
+
+use crate::startup::HmacSecret;
+
+type HmacSha256 = Hmac<sha2::Sha256>;
+
+/// Sign `payload` and return a URL-safe token embedding it.
+///
+/// The `purpose` is part of the authenticated data: a token minted for one
+/// kind of link (e.g. unsubscribing) is rejected when presented to another.
+pub fn sign(secret: &HmacSecret, purpose: &str, payload: &str) -> String {
+    let tag = mac(secret, purpose, payload.as_bytes())
+        .finalize()
+        .into_bytes();
+    format!("{}.{}", URL_SAFE_NO_PAD.encode(payload), hex::encode(tag))
+}
+
+/// Check the signature of a token produced by [`sign`] and return its payload.
+pub fn verify(secret: &HmacSecret, purpose: &str, token: &str) -> Result<String, anyhow::Error> {
+    let (encoded_payload, tag) = token
+        .split_once('.')
+        .context("The token is missing its signature.")?;
+    let payload = URL_SAFE_NO_PAD
+        .decode(encoded_payload)
+        .context("The token payload is not valid base64.")?;
+    let tag = hex::decode(tag).context("The token signature is not valid hex.")?;
+    mac(secret, purpose, &payload)
+        .verify_slice(&tag)
+        .context("The token signature does not match.")?;
+    String::from_utf8(payload).context("The token payload is not valid UTF8.")
+}
+
+fn mac(secret: &HmacSecret, purpose: &str, payload: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.0.expose_secret().as_bytes())
+        .expect("HMAC can take a key of any size");
+    mac.update(purpose.as_bytes());
+    mac.update(&[0]);
+    mac.update(payload);
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sign, verify};
+    use crate::startup::HmacSecret;
+    use claims::{assert_err, assert_ok_eq};
+
+    fn secret() -> HmacSecret {
+        HmacSecret(secrecy::Secret::new("a-very-secret-key".to_string()))
+    }
+
+    #[test]
+    fn a_signed_token_round_trips() {
+        let token = sign(&secret(), "unsubscribe", "some payload");
+        assert_ok_eq!(verify(&secret(), "unsubscribe", &token), "some payload");
+    }
+
+    #[test]
+    fn a_token_is_rejected_for_a_different_purpose() {
+        let token = sign(&secret(), "unsubscribe", "some payload");
+        assert_err!(verify(&secret(), "confirm", &token));
+    }
+
+    #[test]
+    fn a_token_signed_with_another_key_is_rejected() {
+        let other = HmacSecret(secrecy::Secret::new("another-key".to_string()));
+        let token = sign(&other, "unsubscribe", "some payload");
+        assert_err!(verify(&secret(), "unsubscribe", &token));
+    }
+
+    #[test]
+    fn a_tampered_payload_is_rejected() {
+        let token = sign(&secret(), "unsubscribe", "some payload");
+        let (_, tag) = token.split_once('.').unwrap();
+        let forged = format!("{}.{}", "b3RoZXIgcGF5bG9hZA", tag);
+        assert_err!(verify(&secret(), "unsubscribe", &forged));
+    }
+}
@@ -29,13 +29,18 @@ pub struct ApplicationBaseUrl(pub String);
 
 #[derive(Clone)]
 pub struct HmacSecret(pub secrecy::Secret<String>);
+
+#[derive(Clone)]
+pub struct PostmarkWebhookSecret(pub secrecy::Secret<String>);
 // In axum, we have only one state type
 #[derive(Clone)]
 struct AppState {
     email_client: EmailClient,
     connection_pool: sqlx::PgPool,
     base_url: ApplicationBaseUrl,
-    hmac_secret: Key,
+    cookie_key: Key,
+    hmac_secret: HmacSecret,
+    postmark_webhook_secret: PostmarkWebhookSecret,
 }
 
 impl axum::extract::FromRef<AppState> for ApplicationBaseUrl {
@@ -45,9 +50,19 @@ impl axum::extract::FromRef<AppState> for ApplicationBaseUrl {
 }
 impl axum::extract::FromRef<AppState> for axum_extra::extract::cookie::Key {
     fn from_ref(app_state: &AppState) -> axum_extra::extract::cookie::Key {
+        app_state.cookie_key.clone()
+    }
+}
+impl axum::extract::FromRef<AppState> for HmacSecret {
+    fn from_ref(app_state: &AppState) -> HmacSecret {
         app_state.hmac_secret.clone()
     }
 }
+impl axum::extract::FromRef<AppState> for PostmarkWebhookSecret {
+    fn from_ref(app_state: &AppState) -> PostmarkWebhookSecret {
+        app_state.postmark_webhook_secret.clone()
+    }
+}
 impl axum::extract::FromRef<AppState> for EmailClient {
     fn from_ref(app_state: &AppState) -> EmailClient {
         app_state.email_client.clone()
@@ -68,6 +83,7 @@ impl Application {
     pub async fn build(configuration: Settings) -> Result<Self, anyhow::Error> {
         let connection_pool = get_connection_pool(&configuration.database);
 
+        let postmark_webhook_secret = configuration.email_client.webhook_secret.clone();
         let email_client = configuration.email_client.client();
 
         let app = app(
@@ -75,6 +91,7 @@ impl Application {
             email_client,
             configuration.application.base_url,
             configuration.application.hmac_secret,
+            postmark_webhook_secret,
             configuration.redis_uri,
         )
         .await?;
@@ -117,6 +134,7 @@ pub async fn app(
     email_client: EmailClient,
     base_url: String,
     hmac_secret: secrecy::Secret<String>,
+    postmark_webhook_secret: secrecy::Secret<String>,
     redis_uri: secrecy::Secret<String>,
 ) -> Result<Router, anyhow::Error> {
     let cfg = RedisConfig::from_url(redis_uri.expose_secret()).unwrap();
@@ -135,7 +153,9 @@ pub async fn app(
         email_client,
         connection_pool,
         base_url: ApplicationBaseUrl(base_url),
-        hmac_secret: Key::from(hmac_secret.expose_secret().as_bytes()),
+        cookie_key: Key::from(hmac_secret.expose_secret().as_bytes()),
+        hmac_secret: HmacSecret(hmac_secret),
+        postmark_webhook_secret: PostmarkWebhookSecret(postmark_webhook_secret),
     };
 
     let router = Router::new()
@@ -143,7 +163,27 @@ pub async fn app(
         .route("/health_check", get(routes::healt_check))
         .route("/subscriptions", post(routes::subscribe))
         .route("/subscriptions/confirm", get(routes::confirm))
+        .route(
+            "/subscriptions/unsubscribe",
+            get(routes::unsubscribe_form).post(routes::unsubscribe),
+        )
+        .route(
+            "/subscriptions/data_request",
+            get(routes::data_request_form).post(routes::request_subscriber_data),
+        )
+        .route("/subscriptions/data", get(routes::subscriber_data))
+        .route(
+            "/subscriptions/erase",
+            get(routes::erase_subscriber_form).post(routes::erase_subscriber),
+        )
         .route("/login", get(routes::login_form).post(routes::login))
+        .route("/issues", get(routes::list_published_issues))
+        .route("/issues/:newsletter_issue_id", get(routes::published_issue))
+        .route("/feed.rss", get(routes::rss_feed))
+        .route("/feed.atom", get(routes::atom_feed))
+        .route("/t/o/:token", get(routes::track_open))
+        .route("/t/c/:token", get(routes::track_click))
+        .route("/webhooks/postmark", post(routes::postmark_webhook))
         .merge(
             Router::new().nest(
                 "/admin",
@@ -158,6 +198,69 @@ pub async fn app(
                         "/newsletter",
                         get(routes::send_newsletter).post(routes::publish_newsletter),
                     )
+                    .route("/subscribers", get(routes::list_subscribers))
+                    .route("/subscribers.json", get(routes::list_subscribers_json))
+                    .route("/subscribers/export", get(routes::export_subscribers))
+                    .route(
+                        "/subscribers/import",
+                        get(routes::import_subscribers_form).post(routes::import_subscribers),
+                    )
+                    .route(
+                        "/subscribers/:subscriber_id",
+                        get(routes::subscriber_detail),
+                    )
+                    .route(
+                        "/subscribers/:subscriber_id/confirm",
+                        post(routes::confirm_subscriber_manually),
+                    )
+                    .route(
+                        "/subscribers/:subscriber_id/resend_confirmation",
+                        post(routes::resend_confirmation),
+                    )
+                    .route(
+                        "/subscribers/:subscriber_id/edit",
+                        post(routes::update_subscriber),
+                    )
+                    .route(
+                        "/subscribers/:subscriber_id/suspend",
+                        post(routes::suspend_subscriber),
+                    )
+                    .route(
+                        "/subscribers/:subscriber_id/delete",
+                        post(routes::delete_subscriber),
+                    )
+                    .route("/dead_letters", get(routes::dead_letters))
+                    .route("/dead_letters/requeue", post(routes::requeue_dead_letter))
+                    .route(
+                        "/issues",
+                        get(routes::list_issues).post(routes::create_draft),
+                    )
+                    .route("/issues/new", get(routes::new_draft_form))
+                    .route("/issues/:newsletter_issue_id", get(routes::issue_report))
+                    .route(
+                        "/issues/:newsletter_issue_id/edit",
+                        get(routes::edit_draft_form).post(routes::update_draft),
+                    )
+                    .route(
+                        "/issues/:newsletter_issue_id/preview",
+                        get(routes::preview_issue),
+                    )
+                    .route(
+                        "/issues/:newsletter_issue_id/publish",
+                        post(routes::publish_draft),
+                    )
+                    .route(
+                        "/issues/:newsletter_issue_id/test_send",
+                        post(routes::send_test_copy),
+                    )
+                    .route(
+                        "/issues/:newsletter_issue_id/reschedule",
+                        post(routes::reschedule_issue),
+                    )
+                    .route(
+                        "/issues/:newsletter_issue_id/cancel",
+                        post(routes::cancel_issue),
+                    )
                     .layer(axum::middleware::from_fn(reject_anonymous_users)),
             ),
         )
@@ -0,0 +1,136 @@
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{create_confirmed_subscriber, spawn_app, TestApp};
+
+async fn get(app: &TestApp, path: &str) -> reqwest::Response {
+    app.api_client
+        .get(&format!("{}{}", &app.address, path))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn the_archive_lists_published_issues_only() {
+    let app = spawn_app().await;
+    let published_id = app
+        .insert_issue("Published <issue>", "<p>Hi</p>", "published")
+        .await;
+    app.insert_issue("Draft issue", "<p>Hi</p>", "draft").await;
+
+    let response = get(&app, "/issues").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains(&format!("/issues/{}", published_id)));
+    assert!(html_page.contains("Published &lt;issue&gt;"));
+    assert!(!html_page.contains("Draft issue"));
+}
+
+#[tokio::test]
+async fn a_published_issue_is_rendered_sanitised() {
+    let app = spawn_app().await;
+    let newsletter_issue_id = app
+        .insert_issue(
+            "Issue title",
+            "<p>Hi {{ name }}</p><script>alert(1)</script>",
+            "published",
+        )
+        .await;
+
+    let response = get(&app, &format!("/issues/{}", newsletter_issue_id)).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("<p>Hi reader</p>"));
+    assert!(!html_page.contains("alert(1)"));
+}
+
+#[tokio::test]
+async fn unpublished_issues_are_not_in_the_archive() {
+    let app = spawn_app().await;
+    let newsletter_issue_id = app.insert_issue("Draft issue", "<p>Hi</p>", "draft").await;
+
+    for id in [newsletter_issue_id, uuid::Uuid::new_v4()] {
+        let response = get(&app, &format!("/issues/{}", id)).await;
+        assert_eq!(response.status().as_u16(), 404);
+    }
+}
+
+#[tokio::test]
+async fn every_issue_links_to_its_page_in_the_archive() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.publish_issue().await;
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let links: Vec<_> = linkify::LinkFinder::new()
+        .links(body["TextBody"].as_str().unwrap())
+        .map(|l| l.as_str().to_owned())
+        .filter(|l| l.contains("/issues/"))
+        .collect();
+    assert_eq!(links.len(), 1);
+    assert!(body["HtmlBody"].as_str().unwrap().contains(&links[0]));
+
+    let mut issue_link = reqwest::Url::parse(&links[0]).unwrap();
+    issue_link.set_port(Some(app.port)).unwrap();
+    let response = reqwest::get(issue_link).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("<p>Newsletter body as HTML</p>"));
+}
+
+#[tokio::test]
+async fn the_archive_lists_the_most_recent_issues_first() {
+    let app = spawn_app().await;
+    for (title, published_at) in [
+        ("January issue", "2023-01-15T09:00:00Z"),
+        ("March issue", "2023-03-15T09:00:00Z"),
+        ("February issue", "2023-02-15T09:00:00Z"),
+    ] {
+        let published_at: chrono::DateTime<chrono::Utc> = published_at.parse().unwrap();
+        sqlx::query!(
+            r#"
+            INSERT INTO newsletter_issues (
+                newsletter_issue_id, title, text_content, html_content, published_at, status
+            )
+            VALUES ($1, $2, 'Plain text', '<p>Hi</p>', $3, 'published')
+            "#,
+            uuid::Uuid::new_v4(),
+            title,
+            published_at
+        )
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    }
+
+    let html_page = get(&app, "/issues").await.text().await.unwrap();
+
+    let positions: Vec<_> = ["March issue", "February issue", "January issue"]
+        .iter()
+        .map(|title| html_page.find(title).unwrap())
+        .collect();
+    assert!(positions.windows(2).all(|w| w[0] < w[1]));
+    assert!(html_page.contains("2023-03-15 - "));
+}
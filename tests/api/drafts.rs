@@ -0,0 +1,292 @@
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{assert_is_redirect_to, create_confirmed_subscriber, spawn_app, TestApp};
+
+/// Save a new draft and return its id.
+async fn create_draft(app: &TestApp) -> uuid::Uuid {
+    let response = app
+        .post_create_draft(&serde_json::json!({
+            "title": "Draft title",
+            "text_content": "Draft body as plain text",
+            "html_content": "<p>Draft body as HTML</p>",
+        }))
+        .await;
+
+    let newsletter_issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .newsletter_issue_id;
+    assert_is_redirect_to(
+        &response,
+        &format!("/admin/issues/{}/edit", newsletter_issue_id),
+    );
+    newsletter_issue_id
+}
+
+fn publish_body() -> serde_json::Value {
+    serde_json::json!({
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+        "send_at": ""
+    })
+}
+
+#[tokio::test]
+async fn saving_a_draft_does_not_send_anything() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_issue_id = create_draft(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    let saved = sqlx::query!("SELECT status, published_at FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "draft");
+    assert_eq!(saved.published_at, None);
+
+    let html_page = app.get_issues_html().await;
+    assert!(html_page.contains("Draft title"));
+    assert!(html_page.contains(&format!("/admin/issues/{}/edit", newsletter_issue_id)));
+}
+
+#[tokio::test]
+async fn a_draft_can_be_edited() {
+    let app = spawn_app().await;
+    app.login().await;
+    let newsletter_issue_id = create_draft(&app).await;
+
+    let response = app
+        .post_update_draft(
+            newsletter_issue_id,
+            &serde_json::json!({
+                "title": "New <title>",
+                "text_content": "New text",
+                "html_content": "<p>New HTML</p>",
+            }),
+        )
+        .await;
+    assert_is_redirect_to(
+        &response,
+        &format!("/admin/issues/{}/edit", newsletter_issue_id),
+    );
+
+    let html_page = app
+        .get_edit_draft(newsletter_issue_id)
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("The draft has been saved."));
+    assert!(html_page.contains("New &lt;title&gt;"));
+    assert!(html_page.contains("&lt;p&gt;New HTML&lt;/p&gt;"));
+}
+
+#[tokio::test]
+async fn the_preview_renders_both_versions_of_a_draft() {
+    let app = spawn_app().await;
+    app.login().await;
+    let newsletter_issue_id = create_draft(&app).await;
+
+    let html_page = app.get_preview_html(newsletter_issue_id).await;
+
+    assert!(html_page.contains("Draft title"));
+    assert!(
+        html_page.contains(r#"srcdoc="&lt;p&gt;Draft&#x20;body&#x20;as&#x20;HTML&lt;&#x2F;p&gt;""#)
+    );
+    assert!(html_page.contains("<pre>Draft body as plain text</pre>"));
+}
+
+#[tokio::test]
+async fn publishing_a_draft_delivers_it_to_confirmed_subscribers() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    let newsletter_issue_id = create_draft(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_publish_draft(newsletter_issue_id, &publish_body())
+        .await;
+    assert_is_redirect_to(&response, &format!("/admin/issues/{}", newsletter_issue_id));
+    app.dispatch_all_pending_emails().await;
+
+    let html_page = app.get_issue_report_html(newsletter_issue_id).await;
+    assert!(html_page.contains("The newsletter issue has been accepted"));
+    let saved = sqlx::query!("SELECT status FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "published");
+}
+
+#[tokio::test]
+async fn publishing_a_draft_is_idempotent() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    let newsletter_issue_id = create_draft(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let body = publish_body();
+    let response = app.post_publish_draft(newsletter_issue_id, &body).await;
+    assert_is_redirect_to(&response, &format!("/admin/issues/{}", newsletter_issue_id));
+    let response = app.post_publish_draft(newsletter_issue_id, &body).await;
+    assert_is_redirect_to(&response, &format!("/admin/issues/{}", newsletter_issue_id));
+
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn a_published_issue_can_no_longer_be_edited_nor_published_again() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    let newsletter_issue_id = create_draft(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.post_publish_draft(newsletter_issue_id, &publish_body())
+        .await;
+
+    let response = app
+        .post_update_draft(
+            newsletter_issue_id,
+            &serde_json::json!({
+                "title": "New title",
+                "text_content": "New text",
+                "html_content": "<p>New HTML</p>",
+            }),
+        )
+        .await;
+    assert_is_redirect_to(&response, &format!("/admin/issues/{}", newsletter_issue_id));
+    let html_page = app.get_issue_report_html(newsletter_issue_id).await;
+    assert!(html_page.contains("Only drafts can be edited."));
+
+    app.post_publish_draft(newsletter_issue_id, &publish_body())
+        .await;
+    let html_page = app.get_issue_report_html(newsletter_issue_id).await;
+    assert!(html_page.contains("Only drafts can be published."));
+
+    app.dispatch_all_pending_emails().await;
+    let saved = sqlx::query!("SELECT title FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.title, "Draft title");
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_drafts() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_create_draft(&serde_json::json!({
+            "title": "Draft title",
+            "text_content": "Draft body as plain text",
+            "html_content": "<p>Draft body as HTML</p>",
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn a_test_copy_is_sent_to_the_given_addresses_only() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    let newsletter_issue_id = create_draft(&app).await;
+    let n_confirmation_emails = app.email_server.received_requests().await.unwrap().len();
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_send_test_copy(
+            newsletter_issue_id,
+            &serde_json::json!({ "recipients": "alice@example.com,\n bob@example.com" }),
+        )
+        .await;
+    assert_is_redirect_to(&response, &format!("/admin/issues/{}", newsletter_issue_id));
+    app.dispatch_all_pending_emails().await;
+
+    let html_page = app.get_issue_report_html(newsletter_issue_id).await;
+    assert!(html_page.contains("A test copy has been sent to 2 address(es)."));
+    let requests = app.email_server.received_requests().await.unwrap();
+    let recipients: Vec<String> = requests[n_confirmation_emails..]
+        .iter()
+        .map(|r| {
+            let body: serde_json::Value = serde_json::from_slice(&r.body).unwrap();
+            assert_eq!(body["Subject"], "[Test] Draft title");
+            body["To"].as_str().unwrap().to_owned()
+        })
+        .collect();
+    assert_eq!(recipients, ["alice@example.com", "bob@example.com"]);
+
+    // The draft is left untouched, ready to be published.
+    let saved = sqlx::query!("SELECT status FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "draft");
+    let n_queued = sqlx::query!("SELECT COUNT(*) AS \"n!\" FROM issue_delivery_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_queued, 0);
+}
+
+#[tokio::test]
+async fn a_test_copy_is_not_sent_if_an_address_is_invalid() {
+    let app = spawn_app().await;
+    app.login().await;
+    let newsletter_issue_id = create_draft(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_send_test_copy(
+            newsletter_issue_id,
+            &serde_json::json!({ "recipients": "alice@example.com, not-an-email" }),
+        )
+        .await;
+    assert_is_redirect_to(&response, &format!("/admin/issues/{}", newsletter_issue_id));
+
+    let html_page = app.get_issue_report_html(newsletter_issue_id).await;
+    assert!(html_page.contains("not-an-email is not valid subscriber email."));
+}
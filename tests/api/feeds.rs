@@ -0,0 +1,92 @@
+use crate::helpers::{spawn_app, TestApp};
+
+/// Merge tags and markup that the feeds must resolve and escape.
+const ISSUE_HTML: &str = "<p>Hi {{ name }} & co</p>";
+
+async fn get_feed(app: &TestApp, feed: &str, headers: &[(&str, &str)]) -> reqwest::Response {
+    let mut request = app.api_client.get(&format!("{}/{}", &app.address, feed));
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    request.send().await.expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn feeds_contain_published_issues_only() {
+    let app = spawn_app().await;
+    let published_id = app
+        .insert_issue("Published <issue>", ISSUE_HTML, "published")
+        .await;
+    app.insert_issue("Draft issue", ISSUE_HTML, "draft").await;
+
+    for (feed, content_type) in [
+        ("feed.rss", "application/rss+xml; charset=utf-8"),
+        ("feed.atom", "application/atom+xml; charset=utf-8"),
+    ] {
+        let response = get_feed(&app, feed, &[]).await;
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(response.headers()["Content-Type"], content_type);
+        let body = response.text().await.unwrap();
+        assert!(body.contains(&format!("/issues/{}", published_id)));
+        assert!(body.contains("Published &lt;issue&gt;"));
+        assert!(body.contains("&lt;p&gt;Hi reader &amp;amp; co&lt;/p&gt;"));
+        assert!(!body.contains("Draft issue"));
+    }
+}
+
+#[tokio::test]
+async fn a_feed_is_not_sent_again_if_its_etag_matches() {
+    let app = spawn_app().await;
+    app.insert_issue("Published issue", ISSUE_HTML, "published")
+        .await;
+
+    for feed in ["feed.rss", "feed.atom"] {
+        let response = get_feed(&app, feed, &[]).await;
+        let etag = response.headers()["ETag"].to_str().unwrap().to_owned();
+
+        let response = get_feed(&app, feed, &[("If-None-Match", &etag)]).await;
+        assert_eq!(response.status().as_u16(), 304);
+        assert_eq!(response.headers()["ETag"], etag.as_str());
+        assert!(response.text().await.unwrap().is_empty());
+    }
+}
+
+#[tokio::test]
+async fn a_feed_is_not_sent_again_if_not_modified_since() {
+    let app = spawn_app().await;
+    app.insert_issue("Published issue", ISSUE_HTML, "published")
+        .await;
+
+    let response = get_feed(&app, "feed.rss", &[]).await;
+    let last_modified = response.headers()["Last-Modified"]
+        .to_str()
+        .unwrap()
+        .to_owned();
+
+    let response = get_feed(&app, "feed.rss", &[("If-Modified-Since", &last_modified)]).await;
+    assert_eq!(response.status().as_u16(), 304);
+
+    let response = get_feed(
+        &app,
+        "feed.rss",
+        &[("If-Modified-Since", "Sat, 01 Jan 2000 00:00:00 GMT")],
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn publishing_an_issue_changes_the_etag() {
+    let app = spawn_app().await;
+    app.insert_issue("First issue", ISSUE_HTML, "published")
+        .await;
+    let response = get_feed(&app, "feed.atom", &[]).await;
+    let etag = response.headers()["ETag"].to_str().unwrap().to_owned();
+
+    app.insert_issue("Second issue", ISSUE_HTML, "published")
+        .await;
+
+    let response = get_feed(&app, "feed.atom", &[("If-None-Match", &etag)]).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("Second issue"));
+}
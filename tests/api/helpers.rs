@@ -1,13 +1,20 @@
 use argon2::password_hash::SaltString;
 use argon2::{Argon2, PasswordHasher};
+use fake::faker::internet::en::SafeEmail;
+use fake::faker::name::fr_fr::Name;
+use fake::Fake;
 use once_cell::sync::Lazy;
 use sqlx::{Connection, Executor, PgConnection, PgPool};
-use wiremock::MockServer;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
 
-use zero2prod::configuration::{get_configuration, DatabaseSettings};
+use zero2prod::configuration::{get_configuration, DatabaseSettings, IssueDeliverySettings};
 use zero2prod::email_client::EmailClient;
-use zero2prod::issue_delivery_worker::{try_execute_task, ExecutionOutcome};
-use zero2prod::startup::{get_connection_pool, Application};
+use zero2prod::issue_delivery_worker::{
+    publish_due_issues, scheduler_loop, try_execute_task, worker_loop, ExecutionOutcome,
+};
+use zero2prod::rate_limiter::RateLimiter;
+use zero2prod::startup::{get_connection_pool, Application, ApplicationBaseUrl, HmacSecret};
 use zero2prod::telemetry::{get_subscriber, init_subscriber};
 
 // Ensure that the `tracing` stack is only initialised once using `once_cell`
@@ -32,6 +39,13 @@ pub struct ConfirmationLinks {
     pub plain_text: reqwest::Url,
 }
 
+pub struct Subscriber {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub email: String,
+    pub status: String,
+}
+
 pub struct TestApp {
     pub address: String,
     pub db_pool: sqlx::postgres::PgPool,
@@ -40,21 +54,357 @@ pub struct TestApp {
     pub test_user: TestUser,
     pub api_client: reqwest::Client,
     pub email_client: EmailClient,
+    pub base_url: ApplicationBaseUrl,
+    pub hmac_secret: HmacSecret,
+    pub postmark_webhook_secret: secrecy::Secret<String>,
+    pub issue_delivery: IssueDeliverySettings,
+    pub rate_limiter: RateLimiter,
 }
 
 impl TestApp {
+    /// Do what the background worker would: publish the scheduled issues
+    /// that are due, then drain the delivery queue.
     pub async fn dispatch_all_pending_emails(&self) {
+        publish_due_issues(&self.db_pool).await.unwrap();
         loop {
-            if let ExecutionOutcome::EmptyQueue =
-                try_execute_task(&self.db_pool, &self.email_client)
-                    .await
-                    .unwrap()
+            if let ExecutionOutcome::EmptyQueue = try_execute_task(
+                &self.db_pool,
+                &self.email_client,
+                &self.base_url,
+                &self.hmac_secret,
+                &self.issue_delivery,
+                &self.rate_limiter,
+            )
+            .await
+            .unwrap()
             {
                 break;
             }
         }
     }
 
+    /// Run a background delivery worker, as `main` does, for the rest of
+    /// the test.
+    pub fn spawn_delivery_worker(&self, settings: IssueDeliverySettings) {
+        tokio::spawn(worker_loop(
+            self.db_pool.clone(),
+            self.email_client.clone(),
+            self.base_url.clone(),
+            self.hmac_secret.clone(),
+            settings,
+            self.rate_limiter.clone(),
+        ));
+    }
+
+    pub fn spawn_scheduler(&self, check_interval: std::time::Duration) {
+        tokio::spawn(scheduler_loop(self.db_pool.clone(), check_interval));
+    }
+
+    /// Make every task in the delivery queue due right away, skipping
+    /// the backoff applied after failed attempts.
+    pub async fn skip_retry_delays(&self) {
+        sqlx::query!("UPDATE issue_delivery_queue SET execute_after = now()")
+            .execute(&self.db_pool)
+            .await
+            .unwrap();
+    }
+
+    pub async fn get_issue_report(&self, newsletter_issue_id: uuid::Uuid) -> reqwest::Response {
+        self.api_client
+            .get(&format!(
+                "{}/admin/issues/{}",
+                &self.address, newsletter_issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_issue_report_html(&self, newsletter_issue_id: uuid::Uuid) -> String {
+        self.get_issue_report(newsletter_issue_id)
+            .await
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_reschedule_issue<T>(
+        &self,
+        newsletter_issue_id: uuid::Uuid,
+        body: &T,
+    ) -> reqwest::Response
+    where
+        T: serde::Serialize,
+    {
+        self.api_client
+            .post(&format!(
+                "{}/admin/issues/{}/reschedule",
+                &self.address, newsletter_issue_id
+            ))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_cancel_issue(&self, newsletter_issue_id: uuid::Uuid) -> reqwest::Response {
+        self.api_client
+            .post(&format!(
+                "{}/admin/issues/{}/cancel",
+                &self.address, newsletter_issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_issues_html(&self) -> String {
+        self.api_client
+            .get(&format!("{}/admin/issues", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_subscribers(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        self.api_client
+            .get(&format!("{}/admin/subscribers", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_subscribers_html(&self, query: &[(&str, &str)]) -> String {
+        self.get_subscribers(query).await.text().await.unwrap()
+    }
+
+    pub async fn get_subscribers_json(&self, query: &[(&str, &str)]) -> serde_json::Value {
+        self.api_client
+            .get(&format!("{}/admin/subscribers.json", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .json()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_subscribers_export(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        self.api_client
+            .get(&format!("{}/admin/subscribers/export", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_subscriber_detail(&self, subscriber_id: uuid::Uuid) -> reqwest::Response {
+        self.api_client
+            .get(&format!(
+                "{}/admin/subscribers/{}",
+                &self.address, subscriber_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// `action` is one of `confirm`, `resend_confirmation`, `suspend` or `delete`.
+    pub async fn post_subscriber_action(
+        &self,
+        subscriber_id: uuid::Uuid,
+        action: &str,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(&format!(
+                "{}/admin/subscribers/{}/{}",
+                &self.address, subscriber_id, action
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_edit_subscriber<T>(
+        &self,
+        subscriber_id: uuid::Uuid,
+        body: &T,
+    ) -> reqwest::Response
+    where
+        T: serde::Serialize,
+    {
+        self.api_client
+            .post(&format!(
+                "{}/admin/subscribers/{}/edit",
+                &self.address, subscriber_id
+            ))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_subscriber_import(
+        &self,
+        csv: &str,
+        send_confirmations: bool,
+    ) -> reqwest::Response {
+        // The fields go in the order of the form: the checkbox comes first.
+        let mut form = reqwest::multipart::Form::new();
+        if send_confirmations {
+            form = form.text("send_confirmations", "true");
+        }
+        let form = form.part(
+            "file",
+            reqwest::multipart::Part::text(csv.to_string())
+                .file_name("subscribers.csv")
+                .mime_str("text/csv")
+                .unwrap(),
+        );
+        self.api_client
+            .post(&format!("{}/admin/subscribers/import", &self.address))
+            .multipart(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_create_draft<T>(&self, body: &T) -> reqwest::Response
+    where
+        T: serde::Serialize,
+    {
+        self.api_client
+            .post(&format!("{}/admin/issues", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_edit_draft(&self, newsletter_issue_id: uuid::Uuid) -> reqwest::Response {
+        self.api_client
+            .get(&format!(
+                "{}/admin/issues/{}/edit",
+                &self.address, newsletter_issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_update_draft<T>(
+        &self,
+        newsletter_issue_id: uuid::Uuid,
+        body: &T,
+    ) -> reqwest::Response
+    where
+        T: serde::Serialize,
+    {
+        self.api_client
+            .post(&format!(
+                "{}/admin/issues/{}/edit",
+                &self.address, newsletter_issue_id
+            ))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_preview_html(&self, newsletter_issue_id: uuid::Uuid) -> String {
+        self.api_client
+            .get(&format!(
+                "{}/admin/issues/{}/preview",
+                &self.address, newsletter_issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_publish_draft<T>(
+        &self,
+        newsletter_issue_id: uuid::Uuid,
+        body: &T,
+    ) -> reqwest::Response
+    where
+        T: serde::Serialize,
+    {
+        self.api_client
+            .post(&format!(
+                "{}/admin/issues/{}/publish",
+                &self.address, newsletter_issue_id
+            ))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_send_test_copy<T>(
+        &self,
+        newsletter_issue_id: uuid::Uuid,
+        body: &T,
+    ) -> reqwest::Response
+    where
+        T: serde::Serialize,
+    {
+        self.api_client
+            .post(&format!(
+                "{}/admin/issues/{}/test_send",
+                &self.address, newsletter_issue_id
+            ))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_dead_letters_html(&self) -> String {
+        self.api_client
+            .get(&format!("{}/admin/dead_letters", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_requeue_dead_letter<T>(&self, body: &T) -> reqwest::Response
+    where
+        T: serde::Serialize,
+    {
+        self.api_client
+            .post(&format!("{}/admin/dead_letters/requeue", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_postmark_webhook(
+        &self,
+        body: &serde_json::Value,
+        secret: &str,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/webhooks/postmark", &self.address))
+            .header("X-Postmark-Webhook-Secret", secret)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn get_send_newsletter(&self) -> reqwest::Response {
         self.api_client
             .get(&format!("{}/admin/newsletter", &self.address))
@@ -129,6 +479,16 @@ impl TestApp {
             .expect("Failed to execute request")
     }
 
+    /// Log in as the test user.
+    pub async fn login(&self) {
+        let login_body = serde_json::json!({
+            "username": &self.test_user.username,
+            "password": &self.test_user.password
+        });
+        let response = self.post_login(&login_body).await;
+        assert_is_redirect_to(&response, "/admin/dashboard");
+    }
+
     pub async fn post_subscriptions(&self, body: String) -> reqwest::Response {
         self.api_client
             .post(&format!("{}/subscriptions", &self.address))
@@ -139,6 +499,15 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_data_request(&self, email: &str) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/subscriptions/data_request", &self.address))
+            .form(&[("email", email)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn get_publish_newsletter_html(&self) -> String {
         self.get_publish_newsletter().await.text().await.unwrap()
     }
@@ -151,6 +520,88 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    /// Publish an issue with the default content, as a logged-in admin.
+    pub async fn publish_issue(&self) {
+        self.publish_issue_with(serde_json::json!({})).await;
+    }
+
+    /// Publish an issue with the fields of `body`, using the default
+    /// content for the others, as a logged-in admin.
+    pub async fn publish_issue_with(&self, mut body: serde_json::Value) {
+        let defaults = [
+            ("title", "Newsletter title"),
+            ("text_content", "Newsletter body as plain text"),
+            ("html_content", "<p>Newsletter body as HTML</p>"),
+        ];
+        for (field, value) in defaults {
+            if body.get(field).is_none() {
+                body[field] = value.into();
+            }
+        }
+        body["idempotency_key"] = uuid::Uuid::new_v4().to_string().into();
+        let response = self.post_publish_newsletter(&body).await;
+        assert_is_redirect_to(&response, "/admin/newsletter");
+    }
+
+    /// Insert an issue directly, bypassing the delivery queue.
+    pub async fn insert_issue(&self, title: &str, html_content: &str, status: &str) -> uuid::Uuid {
+        let newsletter_issue_id = uuid::Uuid::new_v4();
+        sqlx::query!(
+            r#"
+            INSERT INTO newsletter_issues (
+                newsletter_issue_id, title, text_content, html_content, published_at, status
+            )
+            VALUES ($1, $2, 'Plain text', $3, CASE WHEN $4 = 'published' THEN now() END, $4)
+            "#,
+            newsletter_issue_id,
+            title,
+            html_content,
+            status
+        )
+        .execute(&self.db_pool)
+        .await
+        .unwrap();
+        newsletter_issue_id
+    }
+
+    /// Insert a subscriber directly, as if they subscribed `subscribed_ago`,
+    /// so that the order of listings is known.
+    pub async fn insert_subscriber(
+        &self,
+        name: &str,
+        email: &str,
+        status: &str,
+        subscribed_ago: chrono::Duration,
+    ) -> uuid::Uuid {
+        let id = uuid::Uuid::new_v4();
+        sqlx::query!(
+            r#"
+            INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            id,
+            email,
+            name,
+            chrono::Utc::now() - subscribed_ago,
+            status
+        )
+        .execute(&self.db_pool)
+        .await
+        .unwrap();
+        id
+    }
+
+    /// The one subscriber of the test.
+    pub async fn get_subscriber(&self) -> Subscriber {
+        sqlx::query_as!(
+            Subscriber,
+            "SELECT id, name, email, status FROM subscriptions"
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .unwrap()
+    }
+
     pub async fn post_publish_newsletter<T>(&self, body: &T) -> reqwest::Response
     where
         T: serde::Serialize,
@@ -184,6 +635,45 @@ impl TestApp {
         let plain_text = get_link(&body["TextBody"].as_str().unwrap());
         ConfirmationLinks { html, plain_text }
     }
+
+    /// Extract the data access and erasure links, in that order, from the
+    /// email answering a data request.
+    pub fn get_data_links(
+        &self,
+        email_request: &wiremock::Request,
+    ) -> (reqwest::Url, reqwest::Url) {
+        let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+        let links: Vec<_> = linkify::LinkFinder::new()
+            .links(body["TextBody"].as_str().unwrap())
+            .filter(|l| *l.kind() == linkify::LinkKind::Url)
+            .map(|l| {
+                let mut link = reqwest::Url::parse(l.as_str()).unwrap();
+                assert_eq!(link.host_str().unwrap(), "127.0.0.1");
+                link.set_port(Some(self.port)).unwrap();
+                link
+            })
+            .collect();
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].path(), "/subscriptions/data");
+        assert_eq!(links[1].path(), "/subscriptions/erase");
+        (links[0].clone(), links[1].clone())
+    }
+
+    /// Extract the unsubscribe link appended to a newsletter issue.
+    pub fn get_unsubscribe_link(&self, email_request: &wiremock::Request) -> reqwest::Url {
+        let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+        let links: Vec<_> = linkify::LinkFinder::new()
+            .links(body["TextBody"].as_str().unwrap())
+            .filter(|l| *l.kind() == linkify::LinkKind::Url)
+            .map(|l| l.as_str().to_owned())
+            .filter(|l| l.contains("/subscriptions/unsubscribe"))
+            .collect();
+        assert_eq!(links.len(), 1);
+        let mut unsubscribe_link = reqwest::Url::parse(&links[0]).unwrap();
+        assert_eq!(unsubscribe_link.host_str().unwrap(), "127.0.0.1");
+        unsubscribe_link.set_port(Some(self.port)).unwrap();
+        unsubscribe_link
+    }
 }
 
 pub async fn spawn_app() -> TestApp {
@@ -226,7 +716,12 @@ pub async fn spawn_app() -> TestApp {
         port,
         test_user: TestUser::generate(),
         api_client,
+        postmark_webhook_secret: configuration.email_client.webhook_secret.clone(),
         email_client: configuration.email_client.client(),
+        base_url: ApplicationBaseUrl(configuration.application.base_url),
+        hmac_secret: HmacSecret(configuration.application.hmac_secret),
+        rate_limiter: RateLimiter::new(configuration.issue_delivery.max_sends_per_second),
+        issue_delivery: configuration.issue_delivery,
     };
     test_app.test_user.store(&test_app.db_pool).await;
     test_app
@@ -295,6 +790,63 @@ impl TestUser {
     }
 }
 
+pub async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
+    let name: String = Name().fake();
+    let email: String = SafeEmail().fake();
+    let body = serde_urlencoded::to_string(&serde_json::json!({
+        "name": name,
+        "email": email }))
+    .unwrap();
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .named("Create unconfirmed subscriber")
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    app.post_subscriptions(body.into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let email_request = &app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    app.get_confirmation_links(&email_request)
+}
+
+pub async fn create_confirmed_subscriber(app: &TestApp) {
+    // We can then reuse the same helper and just add
+    // an extra step to actually call the confirmation link!
+    let confirmation_link = create_unconfirmed_subscriber(app).await;
+    reqwest::get(confirmation_link.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+/// Mimic Postmark's batch endpoint: every email in the request is accepted.
+pub fn accept_every_email_in_batch(request: &wiremock::Request) -> ResponseTemplate {
+    let emails: Vec<serde_json::Value> = serde_json::from_slice(&request.body).unwrap();
+    let outcomes: Vec<_> = emails
+        .iter()
+        .map(|_| {
+            serde_json::json!({
+                "ErrorCode": 0,
+                "Message": "OK",
+                "MessageID": uuid::Uuid::new_v4().to_string()
+            })
+        })
+        .collect();
+    ResponseTemplate::new(200).set_body_json(outcomes)
+}
+
 pub fn assert_is_redirect_to(response: &reqwest::Response, location: &str) {
     assert_eq!(response.status().as_u16(), hyper::StatusCode::SEE_OTHER);
     assert_eq!(response.headers().get("Location").unwrap(), location);
@@ -0,0 +1,493 @@
+use std::time::Duration;
+
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::configuration::IssueDeliverySettings;
+
+use crate::helpers::{
+    accept_every_email_in_batch, assert_is_redirect_to, create_confirmed_subscriber, spawn_app,
+    TestApp,
+};
+
+/// Keep attempting the delivery until it ends up in the dead-letter table.
+async fn exhaust_retries(app: &TestApp) {
+    for _ in 0..=app.issue_delivery.max_retries {
+        app.dispatch_all_pending_emails().await;
+        app.skip_retry_delays().await;
+    }
+}
+
+#[tokio::test]
+async fn a_failed_delivery_is_rescheduled_with_a_backoff() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    app.publish_issue().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.dispatch_all_pending_emails().await;
+
+    let task = sqlx::query!(
+        "SELECT n_retries, execute_after > now() AS \"in_the_future!\" FROM issue_delivery_queue"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(task.n_retries, 1);
+    assert!(task.in_the_future);
+}
+
+#[tokio::test]
+async fn a_transient_failure_is_retried_until_the_email_goes_out() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    app.publish_issue().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.dispatch_all_pending_emails().await;
+    app.skip_retry_delays().await;
+    app.dispatch_all_pending_emails().await;
+
+    let n_tasks = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM issue_delivery_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(n_tasks.count, 0);
+}
+
+#[tokio::test]
+async fn a_delivery_that_runs_out_of_retries_is_dead_lettered() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    app.publish_issue().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(app.issue_delivery.max_retries as u64 + 1)
+        .mount(&app.email_server)
+        .await;
+
+    exhaust_retries(&app).await;
+
+    let n_tasks = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM issue_delivery_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(n_tasks.count, 0);
+    let dead_letter = sqlx::query!("SELECT n_retries FROM issue_delivery_dead_letters")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(dead_letter.n_retries, app.issue_delivery.max_retries);
+}
+
+#[tokio::test]
+async fn admins_can_inspect_and_requeue_dead_letters() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    app.publish_issue().await;
+
+    let failing_mock = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount_as_scoped(&app.email_server)
+        .await;
+    exhaust_retries(&app).await;
+    drop(failing_mock);
+
+    let dead_letter = sqlx::query!(
+        "SELECT newsletter_issue_id, subscriber_email FROM issue_delivery_dead_letters"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    let html_page = app.get_dead_letters_html().await;
+    assert!(html_page.contains(&dead_letter.subscriber_email));
+
+    let response = app
+        .post_requeue_dead_letter(&serde_json::json!({
+            "newsletter_issue_id": dead_letter.newsletter_issue_id,
+            "subscriber_email": dead_letter.subscriber_email,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dead_letters");
+    let html_page = app.get_dead_letters_html().await;
+    assert!(html_page.contains("has been requeued"));
+    // The message is only shown once.
+    let html_page = app.get_dead_letters_html().await;
+    assert!(!html_page.contains("has been requeued"));
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    let n_dead_letters =
+        sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM issue_delivery_dead_letters")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(n_dead_letters.count, 0);
+}
+
+#[tokio::test]
+async fn dead_letters_of_subscribers_who_unsubscribed_are_not_requeued() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    app.publish_issue().await;
+
+    let failing_mock = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount_as_scoped(&app.email_server)
+        .await;
+    exhaust_retries(&app).await;
+    drop(failing_mock);
+
+    // The failed attempts still carried the unsubscribe link.
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let unsubscribe_link = app.get_unsubscribe_link(&email_request);
+    app.api_client
+        .post(unsubscribe_link)
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    let dead_letter = sqlx::query!(
+        "SELECT newsletter_issue_id, subscriber_email FROM issue_delivery_dead_letters"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    let response = app
+        .post_requeue_dead_letter(&serde_json::json!({
+            "newsletter_issue_id": dead_letter.newsletter_issue_id,
+            "subscriber_email": dead_letter.subscriber_email,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dead_letters");
+    let html_page = app.get_dead_letters_html().await;
+    assert!(html_page.contains("not requeued: subscriber is no longer confirmed"));
+
+    let n_queued = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM issue_delivery_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(n_queued.count, 0);
+    let n_dead_letters =
+        sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM issue_delivery_dead_letters")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(n_dead_letters.count, 1);
+}
+
+#[tokio::test]
+async fn successful_deliveries_are_logged_with_the_provider_message_id() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    app.publish_issue().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "MessageID": "b7bc2f4a-e38e-4336-af7d-e6c392c2f817",
+            "ErrorCode": 0,
+            "Message": "OK"
+        })))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    let log =
+        sqlx::query!("SELECT outcome, provider_message_id, n_attempts FROM issue_delivery_log")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(log.outcome, "sent");
+    assert_eq!(
+        log.provider_message_id.as_deref(),
+        Some("b7bc2f4a-e38e-4336-af7d-e6c392c2f817")
+    );
+    assert_eq!(log.n_attempts, 1);
+}
+
+#[tokio::test]
+async fn every_attempt_is_counted_in_the_delivery_log() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    app.publish_issue().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    app.dispatch_all_pending_emails().await;
+    let log = sqlx::query!("SELECT outcome, n_attempts FROM issue_delivery_log")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(log.outcome, "retrying");
+    assert_eq!(log.n_attempts, 1);
+
+    app.skip_retry_delays().await;
+    app.dispatch_all_pending_emails().await;
+    let log = sqlx::query!("SELECT outcome, n_attempts FROM issue_delivery_log")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(log.outcome, "sent");
+    assert_eq!(log.n_attempts, 2);
+}
+
+#[tokio::test]
+async fn the_delivery_report_summarises_the_outcome_of_an_issue() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    // A confirmed subscriber we will not be able to deliver to.
+    sqlx::query!(
+        "INSERT INTO subscriptions (id, email, name, subscribed_at, status) \
+        VALUES ($1, 'definitely-not-an-email', 'Ursula', now(), 'confirmed')",
+        uuid::Uuid::new_v4()
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    app.login().await;
+    app.publish_issue().await;
+    let issue = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    let html_page = app
+        .get_issue_report(issue.newsletter_issue_id)
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("<tr><th>Sent</th><td>0</td></tr>"));
+    assert!(html_page.contains("<tr><th>Pending</th><td>2</td></tr>"));
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    let html_page = app
+        .get_issue_report(issue.newsletter_issue_id)
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("<tr><th>Sent</th><td>1</td></tr>"));
+    assert!(html_page.contains("<tr><th>Failed</th><td>1</td></tr>"));
+    assert!(html_page.contains("<tr><th>Pending</th><td>0</td></tr>"));
+}
+
+#[tokio::test]
+async fn the_delivery_report_of_an_unknown_issue_is_a_404() {
+    let app = spawn_app().await;
+    app.login().await;
+
+    let response = app.get_issue_report(uuid::Uuid::new_v4()).await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn an_idle_worker_is_woken_up_as_soon_as_an_issue_is_published() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let n_confirmation_emails = app.email_server.received_requests().await.unwrap().len();
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    // Polling alone would not get the email out before the test gives up.
+    app.spawn_delivery_worker(IssueDeliverySettings {
+        poll_interval_seconds: 3600,
+        ..app.issue_delivery.clone()
+    });
+
+    app.login().await;
+    app.publish_issue().await;
+
+    for _ in 0..50 {
+        let received_requests = app.email_server.received_requests().await.unwrap();
+        if received_requests.len() > n_confirmation_emails {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("The worker did not pick up the new issue.");
+}
+
+#[tokio::test]
+async fn concurrent_workers_deliver_every_email_exactly_once() {
+    let app = spawn_app().await;
+    let n_subscribers = 5;
+    for _ in 0..n_subscribers {
+        create_confirmed_subscriber(&app).await;
+    }
+    let n_confirmation_emails = app.email_server.received_requests().await.unwrap().len();
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(accept_every_email_in_batch)
+        .mount(&app.email_server)
+        .await;
+    for _ in 0..3 {
+        app.spawn_delivery_worker(IssueDeliverySettings {
+            batch_size: 2,
+            ..app.issue_delivery.clone()
+        });
+    }
+
+    app.login().await;
+    app.publish_issue().await;
+
+    let mut n_sent = 0;
+    for _ in 0..50 {
+        n_sent = sqlx::query!(
+            "SELECT COUNT(*) AS \"count!\" FROM issue_delivery_log WHERE outcome = 'sent'"
+        )
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+        if n_sent == n_subscribers {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(n_sent, n_subscribers);
+
+    let n_emails: usize = app.email_server.received_requests().await.unwrap()
+        [n_confirmation_emails..]
+        .iter()
+        .map(|request| match request.url.path() {
+            "/email/batch" => serde_json::from_slice::<Vec<serde_json::Value>>(&request.body)
+                .unwrap()
+                .len(),
+            _ => 1,
+        })
+        .sum();
+    assert_eq!(n_emails, n_subscribers as usize);
+}
+
+#[tokio::test]
+async fn multiple_tasks_are_delivered_with_a_single_batch_request() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    app.publish_issue().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(accept_every_email_in_batch)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.dispatch_all_pending_emails().await;
+
+    let n_sent = sqlx::query!(
+        "SELECT COUNT(*) AS \"count!\" FROM issue_delivery_log \
+        WHERE outcome = 'sent' AND provider_message_id IS NOT NULL"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .count;
+    assert_eq!(n_sent, 2);
+}
+
+#[tokio::test]
+async fn emails_rejected_within_a_batch_are_retried_on_their_own() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    app.publish_issue().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            {"ErrorCode": 0, "Message": "OK", "MessageID": "first-id"},
+            {"ErrorCode": 406, "Message": "Inactive recipient"},
+        ])))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.dispatch_all_pending_emails().await;
+
+    let outcomes = sqlx::query!("SELECT outcome FROM issue_delivery_log ORDER BY outcome")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(outcomes[0].outcome, "retrying");
+    assert_eq!(outcomes[1].outcome, "sent");
+    let task = sqlx::query!("SELECT n_retries FROM issue_delivery_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(task.n_retries, 1);
+}
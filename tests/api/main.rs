@@ -1,8 +1,22 @@
 mod admin_dashboard;
+mod archive;
 mod change_password;
+mod drafts;
+mod feeds;
 mod health_check;
 mod helpers;
+mod issue_delivery;
 mod login;
+mod merge_tags;
 mod newsletter;
+mod postmark_webhook;
+mod scheduled_issues;
+mod subscriber_actions;
+mod subscriber_export;
+mod subscriber_import;
+mod subscribers;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_data;
+mod subscriptions_unsubscribe;
+mod tracking;
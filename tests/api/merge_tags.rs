@@ -0,0 +1,140 @@
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{assert_is_redirect_to, create_confirmed_subscriber, spawn_app};
+
+#[tokio::test]
+async fn merge_tags_are_rendered_for_each_subscriber() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    let subscriber = sqlx::query!("SELECT name, email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Hi {{ name }}, this was sent to {{email}}.",
+        "html_content": r#"<p>Hi {{ name }}</p><a href="{{ unsubscribe_url }}">Leave</a>"#,
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletter");
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let html_body = body["HtmlBody"].as_str().unwrap();
+    let text_body = body["TextBody"].as_str().unwrap();
+    assert!(html_body.starts_with(&format!("<p>Hi {}</p>", subscriber.name)));
+    assert!(text_body.starts_with(&format!(
+        "Hi {}, this was sent to {}.",
+        subscriber.name, subscriber.email
+    )));
+
+    // The template placed the unsubscribe link itself: no default footer.
+    let unsubscribe_link = app.get_unsubscribe_link(&email_request);
+    let unsubscribe_path = format!(
+        "{}?{}",
+        unsubscribe_link.path(),
+        unsubscribe_link.query().unwrap()
+    );
+    assert_eq!(html_body.matches(&unsubscribe_path).count(), 1);
+    assert!(!html_body.contains(">Unsubscribe<"));
+    // The text version did not, so it gets one.
+    assert!(text_body.contains("Unsubscribe: "));
+}
+
+#[tokio::test]
+async fn issues_with_unknown_merge_tags_are_rejected() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Hi {{ first_name }}",
+        "html_content": "<p>Hi {{ name }}</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletter");
+    app.dispatch_all_pending_emails().await;
+
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page
+        .contains("The text content is invalid: {{ first_name }} is not a known merge tag."));
+    let n_issues = sqlx::query!("SELECT COUNT(*) AS \"n!\" FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_issues, 0);
+}
+
+#[tokio::test]
+async fn drafts_with_broken_merge_tags_cannot_be_published() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_create_draft(&serde_json::json!({
+        "title": "Draft title",
+        "text_content": "Hi",
+        "html_content": "<p>Hi {{ name</p>",
+    }))
+    .await;
+    let newsletter_issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .newsletter_issue_id;
+
+    let response = app
+        .post_publish_draft(
+            newsletter_issue_id,
+            &serde_json::json!({
+                "idempotency_key": uuid::Uuid::new_v4().to_string(),
+                "send_at": ""
+            }),
+        )
+        .await;
+    assert_is_redirect_to(&response, &format!("/admin/issues/{}", newsletter_issue_id));
+    app.dispatch_all_pending_emails().await;
+
+    let html_page = app.get_issue_report_html(newsletter_issue_id).await;
+    assert!(
+        html_page.contains("The HTML content is invalid: A merge tag is missing its closing }}.")
+    );
+    let saved = sqlx::query!("SELECT status FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "draft");
+}
@@ -1,7 +1,6 @@
-use crate::helpers::{assert_is_redirect_to, spawn_app, ConfirmationLinks, TestApp};
-use fake::faker::internet::en::SafeEmail;
-use fake::faker::name::fr_fr::Name;
-use fake::Fake;
+use crate::helpers::{
+    assert_is_redirect_to, create_confirmed_subscriber, create_unconfirmed_subscriber, spawn_app,
+};
 use std::time::Duration;
 use wiremock::matchers::{any, method, path};
 use wiremock::{Mock, MockBuilder, ResponseTemplate};
@@ -223,47 +222,6 @@ async fn newsletters_are_delivered_to_confirmed_subscribers() {
     app.dispatch_all_pending_emails().await;
 }
 
-async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
-    let name: String = Name().fake();
-    let email: String = SafeEmail().fake();
-    let body = serde_urlencoded::to_string(&serde_json::json!({
-        "name": name,
-        "email": email }))
-    .unwrap();
-    let _mock_guard = Mock::given(path("/email"))
-        .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
-        .named("Create unconfirmed subscriber")
-        .expect(1)
-        .mount_as_scoped(&app.email_server)
-        .await;
-
-    app.post_subscriptions(body.into())
-        .await
-        .error_for_status()
-        .unwrap();
-
-    let email_request = &app
-        .email_server
-        .received_requests()
-        .await
-        .unwrap()
-        .pop()
-        .unwrap();
-    app.get_confirmation_links(&email_request)
-}
-
-async fn create_confirmed_subscriber(app: &TestApp) {
-    // We can then reuse the same helper and just add
-    // an extra step to actually call the confirmation link!
-    let confirmation_link = create_unconfirmed_subscriber(app).await;
-    reqwest::get(confirmation_link.html)
-        .await
-        .unwrap()
-        .error_for_status()
-        .unwrap();
-}
-
 //#[tokio::test]
 //async fn invalid_password_is_rejected() {
 //    let app = spawn_app().await;
@@ -316,3 +274,166 @@ async fn create_confirmed_subscriber(app: &TestApp) {
 //        response.headers()["WWW-Authenticate"]
 //    );
 //}
+
+#[tokio::test]
+async fn both_versions_of_an_issue_can_be_rendered_from_markdown() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "markdown_content": "# News\n\nRead **this** [post](https://example.com/post).",
+        "html_content": "",
+        "text_content": "",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletter");
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[1];
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let html_body = body["HtmlBody"].as_str().unwrap();
+    assert!(html_body.starts_with(
+        "<h1>News</h1>\n<p>Read <strong>this</strong> <a href=\"https://example.com/post\""
+    ));
+    let text_body = body["TextBody"].as_str().unwrap();
+    assert!(text_body.starts_with("News\n\nRead this post (https://example.com/post)."));
+}
+
+#[tokio::test]
+async fn explicit_content_overrides_the_one_rendered_from_markdown() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "markdown_content": "Markdown *body*",
+        "html_content": "<p>Hand-written HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[1];
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert!(body["HtmlBody"]
+        .as_str()
+        .unwrap()
+        .starts_with("<p>Hand-written HTML</p>"));
+    assert!(body["TextBody"]
+        .as_str()
+        .unwrap()
+        .starts_with("Markdown body"));
+}
+
+#[tokio::test]
+async fn an_issue_without_any_content_is_rejected() {
+    let app = spawn_app().await;
+    app.login().await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "markdown_content": "",
+        "html_content": "<p>HTML only</p>",
+        "text_content": "",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletter");
+
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("Provide a Markdown body, or both HTML and text content."));
+    let n_issues = sqlx::query!("SELECT COUNT(*) AS \"n!\" FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_issues, 0);
+}
+
+#[tokio::test]
+async fn issue_html_is_sanitised_and_its_css_inlined_before_being_stored() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<style>p { color: red; }</style>\
+            <p onmouseover=\"steal()\">Newsletter body</p><script>steal()</script>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletter");
+
+    let saved = sqlx::query!("SELECT html_content FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        saved.html_content,
+        r#"<p style="color: red;">Newsletter body</p>"#
+    );
+
+    app.dispatch_all_pending_emails().await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[1];
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert!(body["HtmlBody"]
+        .as_str()
+        .unwrap()
+        .starts_with(r#"<p style="color: red;">Newsletter body</p>"#));
+}
+
+#[tokio::test]
+async fn issues_whose_html_does_not_parse_are_rejected() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter <b>body</p></div>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletter");
+    app.dispatch_all_pending_emails().await;
+
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("The HTML content could not be parsed:"));
+    let n_issues = sqlx::query!("SELECT COUNT(*) AS \"n!\" FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_issues, 0);
+}
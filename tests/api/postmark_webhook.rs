@@ -0,0 +1,124 @@
+use secrecy::ExposeSecret;
+use wiremock::matchers::any;
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{create_confirmed_subscriber, spawn_app, TestApp};
+
+async fn post_webhook(app: &TestApp, body: serde_json::Value) -> reqwest::Response {
+    app.post_postmark_webhook(&body, app.postmark_webhook_secret.expose_secret())
+        .await
+}
+
+fn bounce(email: &str, bounce_type: &str) -> serde_json::Value {
+    serde_json::json!({
+        "RecordType": "Bounce",
+        "ID": 4323372036854775807u64,
+        "Type": bounce_type,
+        "TypeCode": 1,
+        "Name": "Hard bounce",
+        "MessageID": "883953f4-6105-42a2-a16a-77a8eac79483",
+        "Description": "The server was unable to deliver your message",
+        "Email": email,
+        "From": "sender@example.com",
+        "BouncedAt": "2023-04-27T11:18:21Z",
+        "Inactive": true
+    })
+}
+
+#[tokio::test]
+async fn a_hard_bounce_marks_the_subscriber_as_bounced() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let subscriber = app.get_subscriber().await;
+
+    let response = post_webhook(&app, bounce(&subscriber.email, "HardBounce")).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(app.get_subscriber().await.status, "bounced");
+}
+
+#[tokio::test]
+async fn a_spam_complaint_marks_the_subscriber_as_complained() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let subscriber = app.get_subscriber().await;
+
+    let response = post_webhook(
+        &app,
+        serde_json::json!({
+            "RecordType": "SpamComplaint",
+            "ID": 42,
+            "Type": "SpamComplaint",
+            "TypeCode": 512,
+            "Email": subscriber.email,
+            "BouncedAt": "2023-04-27T11:18:21Z"
+        }),
+    )
+    .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(app.get_subscriber().await.status, "complained");
+}
+
+#[tokio::test]
+async fn soft_bounces_and_other_events_are_ignored() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let subscriber = app.get_subscriber().await;
+
+    let test_cases = vec![
+        bounce(&subscriber.email, "SoftBounce"),
+        serde_json::json!({"RecordType": "Delivery", "Recipient": subscriber.email}),
+    ];
+    for body in test_cases {
+        let response = post_webhook(&app, body).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    assert_eq!(app.get_subscriber().await.status, "confirmed");
+}
+
+#[tokio::test]
+async fn webhooks_without_the_shared_secret_are_rejected() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let subscriber = app.get_subscriber().await;
+
+    let response = app
+        .post_postmark_webhook(&bounce(&subscriber.email, "HardBounce"), "wrong-secret")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(app.get_subscriber().await.status, "confirmed");
+}
+
+#[tokio::test]
+async fn malformed_payloads_are_rejected_with_a_400() {
+    let app = spawn_app().await;
+
+    let response = post_webhook(&app, serde_json::json!({"RecordType": "Bounce"})).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn bounced_subscribers_are_not_mailed_anymore() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let subscriber = app.get_subscriber().await;
+    app.login().await;
+
+    // An issue is on its way when the address bounces.
+    app.publish_issue().await;
+    post_webhook(&app, bounce(&subscriber.email, "HardBounce")).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    // Neither the pending delivery nor the next issue goes out.
+    app.publish_issue_with(serde_json::json!({ "title": "Another title" }))
+        .await;
+    app.dispatch_all_pending_emails().await;
+}
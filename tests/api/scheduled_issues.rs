@@ -0,0 +1,225 @@
+use std::time::Duration;
+
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::configuration::IssueDeliverySettings;
+
+use crate::helpers::{assert_is_redirect_to, create_confirmed_subscriber, spawn_app, TestApp};
+
+/// Schedule an issue for tomorrow and return its id.
+async fn schedule_an_issue(app: &TestApp) -> uuid::Uuid {
+    let send_at = chrono::Utc::now() + chrono::Duration::days(1);
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+        "send_at": send_at.format("%Y-%m-%dT%H:%M").to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletter");
+
+    sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .newsletter_issue_id
+}
+
+/// Pretend the scheduled time of every issue has come.
+async fn make_scheduled_issues_due(app: &TestApp) {
+    sqlx::query!("UPDATE newsletter_issues SET scheduled_for = now() - interval '1 second'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+}
+
+async fn issue_status(app: &TestApp) -> String {
+    sqlx::query!("SELECT status FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .status
+}
+
+#[tokio::test]
+async fn a_scheduled_issue_is_not_delivered_before_its_time() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    schedule_an_issue(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(issue_status(&app).await, "scheduled");
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("The newsletter issue has been scheduled for"));
+}
+
+#[tokio::test]
+async fn a_scheduled_issue_is_delivered_once_its_time_has_come() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    schedule_an_issue(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    make_scheduled_issues_due(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(issue_status(&app).await, "published");
+}
+
+#[tokio::test]
+async fn the_scheduler_publishes_due_issues_and_wakes_up_the_workers() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    let n_confirmation_emails = app.email_server.received_requests().await.unwrap().len();
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    // Polling alone would not get the email out before the test gives up.
+    app.spawn_delivery_worker(IssueDeliverySettings {
+        poll_interval_seconds: 3600,
+        ..app.issue_delivery.clone()
+    });
+    schedule_an_issue(&app).await;
+
+    make_scheduled_issues_due(&app).await;
+    app.spawn_scheduler(Duration::from_millis(100));
+
+    for _ in 0..50 {
+        let received_requests = app.email_server.received_requests().await.unwrap();
+        if received_requests.len() > n_confirmation_emails {
+            assert_eq!(issue_status(&app).await, "published");
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("The scheduled issue was not delivered.");
+}
+
+#[tokio::test]
+async fn a_cancelled_issue_is_never_delivered() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    let issue_id = schedule_an_issue(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_cancel_issue(issue_id).await;
+    assert_is_redirect_to(&response, &format!("/admin/issues/{}", issue_id));
+    let html_page = app.get_issue_report_html(issue_id).await;
+    assert!(html_page.contains("The newsletter issue has been cancelled."));
+
+    make_scheduled_issues_due(&app).await;
+    app.dispatch_all_pending_emails().await;
+    assert_eq!(issue_status(&app).await, "cancelled");
+}
+
+#[tokio::test]
+async fn a_scheduled_issue_can_be_rescheduled() {
+    let app = spawn_app().await;
+    app.login().await;
+    let issue_id = schedule_an_issue(&app).await;
+
+    let response = app
+        .post_reschedule_issue(
+            issue_id,
+            &serde_json::json!({ "send_at": "2099-01-01T09:00:00Z" }),
+        )
+        .await;
+    assert_is_redirect_to(&response, &format!("/admin/issues/{}", issue_id));
+
+    let html_page = app.get_issue_report_html(issue_id).await;
+    assert!(html_page.contains("The newsletter issue has been rescheduled"));
+    assert!(html_page.contains("Scheduled for 2099-01-01 09:00 UTC"));
+}
+
+#[tokio::test]
+async fn an_issue_cannot_be_rescheduled_to_the_past() {
+    let app = spawn_app().await;
+    app.login().await;
+    let issue_id = schedule_an_issue(&app).await;
+
+    let response = app
+        .post_reschedule_issue(
+            issue_id,
+            &serde_json::json!({ "send_at": "2001-01-01T09:00:00Z" }),
+        )
+        .await;
+    assert_is_redirect_to(&response, &format!("/admin/issues/{}", issue_id));
+
+    let html_page = app.get_issue_report_html(issue_id).await;
+    assert!(html_page.contains("2001-01-01 09:00 UTC is in the past."));
+    let issue = sqlx::query!("SELECT status, scheduled_for FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issue.status, "scheduled");
+    assert!(issue.scheduled_for.unwrap() > chrono::Utc::now());
+}
+
+#[tokio::test]
+async fn a_published_issue_cannot_be_cancelled() {
+    let app = spawn_app().await;
+    app.login().await;
+    app.publish_issue().await;
+    let issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .newsletter_issue_id;
+
+    app.post_cancel_issue(issue_id).await;
+
+    let html_page = app.get_issue_report_html(issue_id).await;
+    assert!(html_page.contains("Only scheduled issues can be cancelled."));
+    assert_eq!(issue_status(&app).await, "published");
+}
+
+#[tokio::test]
+async fn an_invalid_send_at_is_rejected() {
+    let app = spawn_app().await;
+    app.login().await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+        "send_at": "next tuesday"
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletter");
+
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("next tuesday is not a valid date and time."));
+    let n_issues = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_issues, 0);
+}
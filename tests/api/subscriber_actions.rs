@@ -0,0 +1,232 @@
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{
+    assert_is_redirect_to, create_confirmed_subscriber, create_unconfirmed_subscriber, spawn_app,
+    TestApp,
+};
+
+async fn get_detail_html(app: &TestApp, subscriber_id: uuid::Uuid) -> String {
+    app.get_subscriber_detail(subscriber_id)
+        .await
+        .text()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_act_on_a_subscriber() {
+    let app = spawn_app().await;
+    create_unconfirmed_subscriber(&app).await;
+    let subscriber = app.get_subscriber().await;
+
+    for action in ["confirm", "resend_confirmation", "suspend", "delete"] {
+        let response = app.post_subscriber_action(subscriber.id, action).await;
+        assert_is_redirect_to(&response, "/login");
+    }
+    let response = app
+        .post_edit_subscriber(
+            subscriber.id,
+            &serde_json::json!({"name": "Mallory", "email": "mallory@example.com"}),
+        )
+        .await;
+    assert_is_redirect_to(&response, "/login");
+
+    let unchanged = app.get_subscriber().await;
+    assert_eq!(unchanged.status, "pending_confirmation");
+    assert_eq!(unchanged.email, subscriber.email);
+}
+
+#[tokio::test]
+async fn a_pending_subscriber_can_be_confirmed_manually() {
+    let app = spawn_app().await;
+    create_unconfirmed_subscriber(&app).await;
+    let subscriber = app.get_subscriber().await;
+    app.login().await;
+
+    let response = app.post_subscriber_action(subscriber.id, "confirm").await;
+
+    assert_is_redirect_to(&response, &format!("/admin/subscribers/{}", subscriber.id));
+    assert_eq!(app.get_subscriber().await.status, "confirmed");
+    let html_page = get_detail_html(&app, subscriber.id).await;
+    assert!(html_page.contains("<p><i>The subscriber has been confirmed.</i></p>"));
+}
+
+#[tokio::test]
+async fn the_confirmation_email_can_be_sent_again() {
+    let app = spawn_app().await;
+    create_unconfirmed_subscriber(&app).await;
+    let subscriber = app.get_subscriber().await;
+    app.login().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_subscriber_action(subscriber.id, "resend_confirmation")
+        .await;
+    assert_is_redirect_to(&response, &format!("/admin/subscribers/{}", subscriber.id));
+    let html_page = get_detail_html(&app, subscriber.id).await;
+    assert!(html_page.contains("A new confirmation email has been sent."));
+
+    // The new link confirms the subscriber.
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_links = app.get_confirmation_links(&email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    assert_eq!(app.get_subscriber().await.status, "confirmed");
+}
+
+#[tokio::test]
+async fn confirmed_subscribers_are_not_sent_a_confirmation_email() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let subscriber = app.get_subscriber().await;
+    app.login().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriber_action(subscriber.id, "resend_confirmation")
+        .await;
+
+    let html_page = get_detail_html(&app, subscriber.id).await;
+    assert!(html_page
+        .contains("Only subscribers pending confirmation can be sent a confirmation email."));
+}
+
+#[tokio::test]
+async fn the_name_and_email_of_a_subscriber_can_be_changed() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let subscriber = app.get_subscriber().await;
+    app.login().await;
+
+    let response = app
+        .post_edit_subscriber(
+            subscriber.id,
+            &serde_json::json!({"name": "Ursula Le Guin", "email": "ursula@example.com"}),
+        )
+        .await;
+
+    assert_is_redirect_to(&response, &format!("/admin/subscribers/{}", subscriber.id));
+    let saved = app.get_subscriber().await;
+    assert_eq!(saved.name, "Ursula Le Guin");
+    assert_eq!(saved.email, "ursula@example.com");
+    let html_page = get_detail_html(&app, subscriber.id).await;
+    assert!(html_page.contains("The subscriber details have been saved."));
+}
+
+#[tokio::test]
+async fn invalid_or_taken_details_are_rejected() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let subscriber = app.get_subscriber().await;
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+        VALUES ($1, 'taken@example.com', 'Someone else', now(), 'confirmed')
+        "#,
+        uuid::Uuid::new_v4()
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    app.login().await;
+
+    let test_cases = vec![
+        (
+            serde_json::json!({"name": "Ursula", "email": "not-an-email"}),
+            "not-an-email is not valid subscriber email.",
+        ),
+        (
+            serde_json::json!({"name": "<script>", "email": "ursula@example.com"}),
+            "&lt;script&gt; is not a valid subscriber name.",
+        ),
+        (
+            serde_json::json!({"name": "Ursula", "email": "taken@example.com"}),
+            "Another subscriber already uses taken@example.com.",
+        ),
+    ];
+    for (body, error_message) in test_cases {
+        app.post_edit_subscriber(subscriber.id, &body).await;
+        let html_page = get_detail_html(&app, subscriber.id).await;
+        assert!(html_page.contains(error_message), "{}", error_message);
+    }
+
+    let unchanged = sqlx::query!(
+        "SELECT name, email FROM subscriptions WHERE id = $1",
+        subscriber.id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(unchanged.name, subscriber.name);
+    assert_eq!(unchanged.email, subscriber.email);
+}
+
+#[tokio::test]
+async fn suspended_subscribers_are_not_mailed_until_confirmed_again() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let subscriber = app.get_subscriber().await;
+    app.login().await;
+
+    let response = app.post_subscriber_action(subscriber.id, "suspend").await;
+    assert_is_redirect_to(&response, &format!("/admin/subscribers/{}", subscriber.id));
+    assert_eq!(app.get_subscriber().await.status, "suspended");
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .named("No delivery to suspended subscribers")
+        .mount(&app.email_server)
+        .await;
+    app.publish_issue().await;
+    app.dispatch_all_pending_emails().await;
+
+    app.post_subscriber_action(subscriber.id, "confirm").await;
+    assert_eq!(app.get_subscriber().await.status, "confirmed");
+}
+
+#[tokio::test]
+async fn a_subscriber_can_be_deleted() {
+    let app = spawn_app().await;
+    create_unconfirmed_subscriber(&app).await;
+    let subscriber = app.get_subscriber().await;
+    app.login().await;
+
+    let response = app.post_subscriber_action(subscriber.id, "delete").await;
+
+    assert_is_redirect_to(&response, "/admin/subscribers");
+    let html_page = app.get_subscribers_html(&[]).await;
+    assert!(html_page.contains(&format!("{} has been deleted.", subscriber.email)));
+    let n_subscribers = sqlx::query!("SELECT COUNT(*) AS \"n!\" FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_subscribers, 0);
+    let n_tokens = sqlx::query!("SELECT COUNT(*) AS \"n!\" FROM subscription_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_tokens, 0);
+    let response = app.get_subscriber_detail(subscriber.id).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
@@ -0,0 +1,133 @@
+use chrono::Duration;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_export_subscribers() {
+    let app = spawn_app().await;
+
+    let response = app.get_subscribers_export(&[]).await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn subscribers_are_exported_as_csv_oldest_first() {
+    let app = spawn_app().await;
+    app.insert_subscriber(
+        "Le Guin, Ursula",
+        "ursula@example.com",
+        "confirmed",
+        Duration::days(1),
+    )
+    .await;
+    app.insert_subscriber(
+        "Octavia",
+        "octavia@example.com",
+        "unsubscribed",
+        Duration::days(2),
+    )
+    .await;
+    app.login().await;
+
+    let response = app.get_subscribers_export(&[("format", "csv")]).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/csv; charset=utf-8"
+    );
+    let csv = response.text().await.unwrap();
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], "id,email,name,status,subscribed_at");
+    assert!(lines[1].contains(",octavia@example.com,Octavia,unsubscribed,"));
+    // Fields containing commas are quoted.
+    assert!(lines[2].contains(r#",ursula@example.com,"Le Guin, Ursula",confirmed,"#));
+}
+
+#[tokio::test]
+async fn formulas_are_defused_in_the_csv_export() {
+    let app = spawn_app().await;
+    app.insert_subscriber(
+        "=HYPERLINK(\"https://evil.example.com\")",
+        "@ursula@example.com",
+        "confirmed",
+        Duration::days(1),
+    )
+    .await;
+    app.login().await;
+
+    let response = app.get_subscribers_export(&[("format", "csv")]).await;
+
+    let csv = response.text().await.unwrap();
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert!(lines[1].contains(
+        r#",'@ursula@example.com,"'=HYPERLINK(""https://evil.example.com"")",confirmed,"#
+    ));
+}
+
+#[tokio::test]
+async fn subscribers_can_be_exported_as_json_lines_filtered_by_status() {
+    let app = spawn_app().await;
+    app.insert_subscriber(
+        "Ursula",
+        "ursula@example.com",
+        "confirmed",
+        Duration::days(1),
+    )
+    .await;
+    app.insert_subscriber(
+        "Octavia",
+        "octavia@example.com",
+        "unsubscribed",
+        Duration::days(2),
+    )
+    .await;
+    app.insert_subscriber("Iain", "iain@example.com", "confirmed", Duration::days(3))
+        .await;
+    app.login().await;
+
+    let response = app
+        .get_subscribers_export(&[("format", "ndjson"), ("status", "confirmed")])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+    let body = response.text().await.unwrap();
+    let subscribers = body
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    let emails = subscribers
+        .iter()
+        .map(|subscriber| subscriber["email"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(emails, vec!["iain@example.com", "ursula@example.com"]);
+    assert_eq!(subscribers[0]["name"], "Iain");
+    assert_eq!(subscribers[0]["status"], "confirmed");
+}
+
+#[tokio::test]
+async fn an_empty_export_only_has_the_csv_header() {
+    let app = spawn_app().await;
+    app.login().await;
+
+    let response = app.get_subscribers_export(&[]).await;
+
+    assert_eq!(
+        response.text().await.unwrap(),
+        "id,email,name,status,subscribed_at\n"
+    );
+}
+
+#[tokio::test]
+async fn unknown_statuses_and_formats_are_rejected() {
+    let app = spawn_app().await;
+    app.login().await;
+
+    let response = app.get_subscribers_export(&[("status", "vip")]).await;
+    assert_eq!(response.status().as_u16(), 400);
+    let response = app.get_subscribers_export(&[("format", "xml")]).await;
+    assert_eq!(response.status().as_u16(), 400);
+}
@@ -0,0 +1,206 @@
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{assert_is_redirect_to, create_confirmed_subscriber, spawn_app, TestApp};
+
+struct Subscriber {
+    name: String,
+    email: String,
+    status: String,
+}
+
+async fn get_subscribers(app: &TestApp) -> Vec<Subscriber> {
+    sqlx::query_as!(
+        Subscriber,
+        "SELECT name, email, status FROM subscriptions ORDER BY email"
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_import_subscribers() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_subscriber_import("name,email\nUrsula,ursula@example.com\n", false)
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+    assert!(get_subscribers(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn valid_rows_are_imported() {
+    let app = spawn_app().await;
+    app.login().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    let csv = "\
+Email,Name,Status
+ursula@example.com,Ursula Le Guin,
+octavia@example.com , Octavia Butler ,confirmed
+iain@example.com,Iain Banks,unsubscribed
+";
+
+    let response = app.post_subscriber_import(csv, false).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("3 subscribers imported, 0 confirmation emails sent."));
+    assert!(html_page.contains("Every row was imported."));
+    let imported = get_subscribers(&app)
+        .await
+        .into_iter()
+        .map(|s| (s.email, s.name, s.status))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        imported,
+        vec![
+            (
+                "iain@example.com".to_string(),
+                "Iain Banks".to_string(),
+                "unsubscribed".to_string()
+            ),
+            (
+                "octavia@example.com".to_string(),
+                "Octavia Butler".to_string(),
+                "confirmed".to_string()
+            ),
+            (
+                "ursula@example.com".to_string(),
+                "Ursula Le Guin".to_string(),
+                "pending_confirmation".to_string()
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn invalid_and_duplicate_rows_are_reported_and_skipped() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let existing_email = get_subscribers(&app).await.pop().unwrap().email;
+    app.login().await;
+    let csv = format!(
+        "\
+name,email,status
+Ursula,ursula@example.com,
+Ursula again,ursula@example.com,
+,nameless@example.com,
+Mallory,not-an-email,
+Bounced,bounced@example.com,bounced
+Existing,{},
+Octavia,octavia@example.com,confirmed
+",
+        existing_email
+    );
+
+    let response = app.post_subscriber_import(&csv, false).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("2 subscribers imported"));
+    let expected_errors = [
+        "<tr><td>3</td><td>ursula@example.com</td><td>This address is already subscribed.</td></tr>",
+        "<tr><td>4</td><td>nameless@example.com</td><td> is not a valid subscriber name.</td></tr>",
+        "<tr><td>5</td><td>not-an-email</td><td>not-an-email is not valid subscriber email.</td></tr>",
+        "<tr><td>6</td><td>bounced@example.com</td><td>bounced is not a status subscribers can be imported with.</td></tr>",
+    ];
+    for error in expected_errors {
+        assert!(html_page.contains(error), "{}", error);
+    }
+    assert!(html_page.contains(&format!(
+        "<tr><td>7</td><td>{}</td><td>This address is already subscribed.</td></tr>",
+        existing_email
+    )));
+    let emails = get_subscribers(&app)
+        .await
+        .into_iter()
+        .map(|s| s.email)
+        .collect::<Vec<_>>();
+    assert_eq!(emails.len(), 3);
+    assert!(emails.contains(&"ursula@example.com".to_string()));
+    assert!(emails.contains(&"octavia@example.com".to_string()));
+}
+
+#[tokio::test]
+async fn a_file_without_name_and_email_columns_is_rejected() {
+    let app = spawn_app().await;
+    app.login().await;
+
+    let response = app
+        .post_subscriber_import("first_name,address\nUrsula,ursula@example.com\n", false)
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert!(get_subscribers(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn confirmation_emails_can_be_sent_to_pending_subscribers() {
+    let app = spawn_app().await;
+    app.login().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+    let csv = "\
+name,email,status
+Ursula,ursula@example.com,
+Octavia,octavia@example.com,confirmed
+Iain,iain@example.com,pending_confirmation
+";
+
+    let response = app.post_subscriber_import(csv, true).await;
+
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("3 subscribers imported, 2 confirmation emails sent."));
+    // The links confirm the imported subscribers.
+    for email_request in app.email_server.received_requests().await.unwrap() {
+        let confirmation_links = app.get_confirmation_links(&email_request);
+        reqwest::get(confirmation_links.html)
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap();
+    }
+    assert!(get_subscribers(&app)
+        .await
+        .iter()
+        .all(|s| s.status == "confirmed"));
+}
+
+#[tokio::test]
+async fn an_import_sends_at_most_100_confirmation_emails() {
+    let app = spawn_app().await;
+    app.login().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(100)
+        .mount(&app.email_server)
+        .await;
+    let mut csv = "name,email\n".to_string();
+    for i in 0..101 {
+        csv.push_str(&format!("Subscriber {i},subscriber{i}@example.com\n"));
+    }
+
+    let response = app.post_subscriber_import(&csv, true).await;
+
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("101 subscribers imported, 100 confirmation emails sent."));
+    assert!(html_page.contains("1 subscribers are pending confirmation without one"));
+    let n_tokens = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM subscription_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_tokens, 100);
+}
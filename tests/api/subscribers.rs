@@ -0,0 +1,229 @@
+use std::collections::HashSet;
+
+use chrono::Duration;
+use uuid::Uuid;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+fn emails(page: &serde_json::Value) -> Vec<&str> {
+    page["subscribers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|subscriber| subscriber["email"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_subscribers() {
+    let app = spawn_app().await;
+
+    let response = app.get_subscribers(&[]).await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn subscribers_are_listed_newest_first() {
+    let app = spawn_app().await;
+    app.login().await;
+    app.insert_subscriber(
+        "Ursula",
+        "ursula@example.com",
+        "confirmed",
+        Duration::minutes(10),
+    )
+    .await;
+    app.insert_subscriber(
+        "Tom & Jerry",
+        "tom@example.com",
+        "pending_confirmation",
+        Duration::minutes(5),
+    )
+    .await;
+
+    let html_page = app.get_subscribers_html(&[]).await;
+    assert!(html_page.contains("Tom &amp; Jerry"));
+    assert!(html_page.contains("<td>pending_confirmation</td>"));
+    assert!(
+        html_page.find("tom@example.com").unwrap() < html_page.find("ursula@example.com").unwrap()
+    );
+
+    let page = app.get_subscribers_json(&[]).await;
+    assert_eq!(emails(&page), vec!["tom@example.com", "ursula@example.com"]);
+    assert_eq!(page["subscribers"][1]["name"], "Ursula");
+    assert_eq!(page["subscribers"][1]["status"], "confirmed");
+    assert!(page["subscribers"][1]["subscribed_at"].is_string());
+    assert!(page["next_cursor"].is_null());
+}
+
+#[tokio::test]
+async fn subscribers_can_be_searched_by_name_or_email() {
+    let app = spawn_app().await;
+    app.login().await;
+    app.insert_subscriber(
+        "Ursula Le Guin",
+        "ursula@example.com",
+        "confirmed",
+        Duration::minutes(3),
+    )
+    .await;
+    app.insert_subscriber(
+        "Tom",
+        "tom@books.example.com",
+        "confirmed",
+        Duration::minutes(2),
+    )
+    .await;
+    app.insert_subscriber(
+        "Ann_Leckie",
+        "ann@example.com",
+        "confirmed",
+        Duration::minutes(1),
+    )
+    .await;
+
+    let test_cases = vec![
+        ("le guin", vec!["ursula@example.com"]),
+        ("BOOKS", vec!["tom@books.example.com"]),
+        // Wildcards are matched literally.
+        ("_", vec!["ann@example.com"]),
+        ("%", vec![]),
+        (
+            "",
+            vec![
+                "ann@example.com",
+                "tom@books.example.com",
+                "ursula@example.com",
+            ],
+        ),
+    ];
+    for (search, expected) in test_cases {
+        let page = app.get_subscribers_json(&[("q", search)]).await;
+        assert_eq!(emails(&page), expected, "Searching for {:?}", search);
+    }
+}
+
+#[tokio::test]
+async fn subscribers_can_be_filtered_by_status() {
+    let app = spawn_app().await;
+    app.login().await;
+    app.insert_subscriber(
+        "Ursula",
+        "ursula@example.com",
+        "confirmed",
+        Duration::minutes(2),
+    )
+    .await;
+    app.insert_subscriber("Tom", "tom@example.com", "bounced", Duration::minutes(1))
+        .await;
+
+    let page = app.get_subscribers_json(&[("status", "bounced")]).await;
+    assert_eq!(emails(&page), vec!["tom@example.com"]);
+
+    let html_page = app.get_subscribers_html(&[("status", "bounced")]).await;
+    assert!(html_page.contains(r#"<option value="bounced" selected>"#));
+    assert!(!html_page.contains("ursula@example.com"));
+}
+
+#[tokio::test]
+async fn unknown_statuses_and_malformed_cursors_are_rejected_with_a_400() {
+    let app = spawn_app().await;
+    app.login().await;
+
+    for query in [[("status", "vip")], [("after", "not-a-cursor")]] {
+        let response = app.get_subscribers(&query).await;
+        assert_eq!(response.status().as_u16(), 400, "Query: {:?}", query);
+    }
+}
+
+#[tokio::test]
+async fn subscribers_are_paginated() {
+    let app = spawn_app().await;
+    app.login().await;
+    for i in 0..120 {
+        let email = format!("subscriber{}@example.com", i);
+        app.insert_subscriber("Subscriber", &email, "confirmed", Duration::minutes(i))
+            .await;
+    }
+
+    let html_page = app.get_subscribers_html(&[]).await;
+    assert!(html_page.contains("Next page"));
+
+    let mut seen = Vec::new();
+    let mut cursor = None;
+    loop {
+        let query: Vec<_> = cursor
+            .iter()
+            .map(|c: &String| ("after", c.as_str()))
+            .collect();
+        let page = app.get_subscribers_json(&query).await;
+        seen.extend(emails(&page).into_iter().map(str::to_string));
+        match page["next_cursor"].as_str() {
+            Some(next_cursor) => cursor = Some(next_cursor.to_string()),
+            None => break,
+        }
+    }
+    let expected: Vec<_> = (0..120)
+        .map(|i| format!("subscriber{}@example.com", i))
+        .collect();
+    assert_eq!(seen, expected);
+    assert_eq!(seen.iter().collect::<HashSet<_>>().len(), 120);
+}
+
+#[tokio::test]
+async fn the_detail_view_shows_a_subscriber_and_their_deliveries() {
+    let app = spawn_app().await;
+    app.login().await;
+    let subscriber_id = app
+        .insert_subscriber(
+            "Ursula",
+            "ursula@example.com",
+            "confirmed",
+            Duration::minutes(1),
+        )
+        .await;
+    let newsletter_issue_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issues (
+            newsletter_issue_id, title, text_content, html_content, published_at
+        )
+        VALUES ($1, 'First <issue>', 'Text', '<p>Html</p>', now())
+        "#,
+        newsletter_issue_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_log (
+            newsletter_issue_id, subscriber_email, outcome, n_attempts,
+            first_attempted_at, last_attempted_at
+        )
+        VALUES ($1, 'ursula@example.com', 'sent', 1, now(), now())
+        "#,
+        newsletter_issue_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let html_page = app.get_subscribers_html(&[]).await;
+    assert!(html_page.contains(&format!(
+        r#"<a href="/admin/subscribers/{}">"#,
+        subscriber_id
+    )));
+
+    let response = app.get_subscriber_detail(subscriber_id).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("<h1>Ursula</h1>"));
+    assert!(html_page.contains("<td>ursula@example.com</td>"));
+    assert!(html_page.contains("<td>confirmed</td>"));
+    assert!(html_page.contains("First &lt;issue&gt;</a></td><td>sent</td>"));
+
+    let response = app.get_subscriber_detail(Uuid::new_v4()).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
@@ -150,3 +150,88 @@ async fn subscribe_fails_if_there_is_a_fatal_database_error() {
     // Assert
     assert_eq!(response.status().as_u16(), 500);
 }
+
+#[tokio::test]
+async fn subscribing_twice_before_confirming_sends_a_new_confirmation_email() {
+    let app = spawn_app().await;
+    let body = "name=serj&email=serj%40rodrigess.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_subscriptions(body.into()).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app.post_subscriptions(body.into()).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    let first_link = app.get_confirmation_links(&email_requests[0]);
+    let second_link = app.get_confirmation_links(&email_requests[1]);
+    assert_ne!(first_link.html, second_link.html);
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].status, "pending_confirmation");
+}
+
+#[tokio::test]
+async fn concurrent_submissions_of_a_new_address_all_succeed() {
+    let app = spawn_app().await;
+    let body = "name=serj&email=serj%40rodrigess.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let responses =
+        futures_util::future::join_all((0..5).map(|_| app.post_subscriptions(body.into()))).await;
+
+    for response in responses {
+        assert_eq!(response.status().as_u16(), 200);
+    }
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.len(), 1);
+}
+
+#[tokio::test]
+async fn subscribing_again_after_unsubscribing_asks_for_a_new_confirmation() {
+    let app = spawn_app().await;
+    let body = "name=serj&email=serj%40rodrigess.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions(body.into())
+        .await
+        .error_for_status()
+        .unwrap();
+    sqlx::query!("UPDATE subscriptions SET status = 'unsubscribed'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = app.post_subscriptions(body.into()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "pending_confirmation");
+}
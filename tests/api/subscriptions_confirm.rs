@@ -65,3 +65,60 @@ async fn clicking_on_the_confirmation_link_confirms_a_subscriber() {
     assert_eq!(saved.name, "serj");
     assert_eq!(saved.status, "confirmed");
 }
+
+#[tokio::test]
+async fn expired_confirmation_links_are_rejected_with_a_410() {
+    let app = spawn_app().await;
+    let body = "name=serj&email=lserj%40rodrigess.com";
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions(body.into()).await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    sqlx::query!("UPDATE subscription_tokens SET created_at = now() - interval '25 hours'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = reqwest::get(confirmation_links.html).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 410);
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "pending_confirmation");
+}
+
+#[tokio::test]
+async fn a_resent_confirmation_link_works_after_the_first_one_expired() {
+    let app = spawn_app().await;
+    let body = "name=serj&email=lserj%40rodrigess.com";
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions(body.into()).await;
+    sqlx::query!("UPDATE subscription_tokens SET created_at = now() - interval '25 hours'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.post_subscriptions(body.into()).await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[1];
+    let confirmation_links = app.get_confirmation_links(email_request);
+
+    let response = reqwest::get(confirmation_links.html).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "confirmed");
+}
@@ -0,0 +1,186 @@
+use std::time::Duration;
+
+use wiremock::{
+    matchers::{any, method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{create_confirmed_subscriber, spawn_app, TestApp};
+
+async fn subscriber_email(app: &TestApp) -> String {
+    sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .email
+}
+
+/// A confirmed subscriber who received one issue and has another one
+/// waiting in the delivery queue.
+async fn subscriber_with_history(app: &TestApp) -> String {
+    create_confirmed_subscriber(app).await;
+    app.login().await;
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.publish_issue().await;
+    app.dispatch_all_pending_emails().await;
+    app.publish_issue().await;
+    subscriber_email(app).await
+}
+
+/// Wait for the background task of a data request to email the links.
+async fn wait_for_email(app: &TestApp, n_emails_before: usize) -> wiremock::Request {
+    for _ in 0..50 {
+        let mut received_requests = app.email_server.received_requests().await.unwrap();
+        if received_requests.len() > n_emails_before {
+            return received_requests.pop().unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("The data access and erasure links were not sent.");
+}
+
+/// Ask for the links of `email` and return them.
+async fn request_data_links(app: &TestApp, email: &str) -> (reqwest::Url, reqwest::Url) {
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    let n_emails_before = app.email_server.received_requests().await.unwrap().len();
+    app.post_data_request(email)
+        .await
+        .error_for_status()
+        .unwrap();
+    let email_request = wait_for_email(app, n_emails_before).await;
+    app.get_data_links(&email_request)
+}
+
+#[tokio::test]
+async fn unknown_addresses_get_the_same_answer_but_no_email() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let email = subscriber_email(&app).await;
+    let n_emails_before = app.email_server.received_requests().await.unwrap().len();
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let stranger_response = app.post_data_request("stranger@example.com").await;
+    let subscriber_response = app.post_data_request(&email).await;
+
+    assert_eq!(stranger_response.status().as_u16(), 200);
+    assert_eq!(subscriber_response.status().as_u16(), 200);
+    let stranger_page = stranger_response.text().await.unwrap();
+    assert!(stranger_page.contains("If this address is on our list"));
+    assert_eq!(stranger_page, subscriber_response.text().await.unwrap());
+    // Only the subscriber gets an email. Give a stray one time to show up.
+    let email_request = wait_for_email(&app, n_emails_before).await;
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(&email_request.body).unwrap()["To"],
+        email
+    );
+    tokio::time::sleep(Duration::from_millis(200)).await;
+}
+
+#[tokio::test]
+async fn the_access_link_returns_everything_stored_about_the_subscriber() {
+    let app = spawn_app().await;
+    let email = subscriber_with_history(&app).await;
+    let (access_link, _) = request_data_links(&app, &email).await;
+
+    let response = reqwest::get(access_link).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    let data: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(data["subscription"]["email"], email);
+    assert_eq!(data["subscription"]["status"], "confirmed");
+    assert_eq!(data["subscription_tokens"].as_array().unwrap().len(), 1);
+    assert_eq!(data["deliveries"].as_array().unwrap().len(), 1);
+    assert_eq!(data["queued_deliveries"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn following_the_erasure_link_does_not_erase_on_its_own() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let email = subscriber_email(&app).await;
+    let (_, erasure_link) = request_data_links(&app, &email).await;
+
+    let response = reqwest::get(erasure_link).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("Erase my data"));
+    assert_eq!(subscriber_email(&app).await, email);
+}
+
+#[tokio::test]
+async fn erasure_removes_the_subscriber_and_anonymises_their_deliveries() {
+    let app = spawn_app().await;
+    let email = subscriber_with_history(&app).await;
+    let (access_link, erasure_link) = request_data_links(&app, &email).await;
+
+    let response = reqwest::Client::new()
+        .post(erasure_link.clone())
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    let remaining = sqlx::query!(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM subscriptions) AS "subscriptions!",
+            (SELECT COUNT(*) FROM subscription_tokens) AS "tokens!",
+            (SELECT COUNT(*) FROM issue_delivery_queue) AS "queued!",
+            (SELECT COUNT(*) FROM issue_delivery_log) AS "logged!",
+            (SELECT COUNT(*) FROM issue_delivery_log WHERE subscriber_email = $1) AS "logged_for_email!"
+        "#,
+        email
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(remaining.subscriptions, 0);
+    assert_eq!(remaining.tokens, 0);
+    assert_eq!(remaining.queued, 0);
+    // Issue reports still count the delivery.
+    assert_eq!(remaining.logged, 1);
+    assert_eq!(remaining.logged_for_email, 0);
+
+    // The links are useless once the subscriber is gone.
+    let response = reqwest::get(access_link).await.unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+    let response = reqwest::Client::new()
+        .post(erasure_link)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn a_link_only_works_for_its_own_purpose() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let email = subscriber_email(&app).await;
+    let (access_link, erasure_link) = request_data_links(&app, &email).await;
+
+    let mut access_token_on_erasure = erasure_link.clone();
+    access_token_on_erasure.set_query(access_link.query());
+    let response = reqwest::Client::new()
+        .post(access_token_on_erasure)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(subscriber_email(&app).await, email);
+}
@@ -0,0 +1,186 @@
+use wiremock::{
+    matchers::{any, method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{create_confirmed_subscriber, spawn_app, TestApp};
+
+/// Publish an issue to a single confirmed subscriber and return the
+/// request that was fired at the email provider.
+async fn deliver_an_issue(app: &TestApp) -> wiremock::Request {
+    create_confirmed_subscriber(app).await;
+    app.login().await;
+
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.publish_issue().await;
+    app.dispatch_all_pending_emails().await;
+
+    app.email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap()
+}
+
+async fn unsubscribe_link_from_an_issue(app: &TestApp) -> reqwest::Url {
+    let email_request = deliver_an_issue(app).await;
+    app.get_unsubscribe_link(&email_request)
+}
+
+#[tokio::test]
+async fn every_issue_contains_an_unsubscribe_link() {
+    let app = spawn_app().await;
+
+    let unsubscribe_link = unsubscribe_link_from_an_issue(&app).await;
+
+    let response = reqwest::get(unsubscribe_link).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("Unsubscribe"));
+}
+
+#[tokio::test]
+async fn following_the_unsubscribe_link_does_not_unsubscribe_on_its_own() {
+    let app = spawn_app().await;
+    let unsubscribe_link = unsubscribe_link_from_an_issue(&app).await;
+
+    reqwest::get(unsubscribe_link)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "confirmed");
+}
+
+#[tokio::test]
+async fn confirming_the_unsubscription_updates_the_subscriber_status() {
+    let app = spawn_app().await;
+    let unsubscribe_link = unsubscribe_link_from_an_issue(&app).await;
+
+    let response = app.api_client.post(unsubscribe_link).send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "unsubscribed");
+}
+
+#[tokio::test]
+async fn unsubscribed_subscribers_do_not_receive_further_issues() {
+    let app = spawn_app().await;
+    let unsubscribe_link = unsubscribe_link_from_an_issue(&app).await;
+    app.api_client
+        .post(unsubscribe_link)
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    app.publish_issue().await;
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn unsubscribing_with_a_tampered_token_is_rejected_with_a_401() {
+    let app = spawn_app().await;
+    let mut unsubscribe_link = unsubscribe_link_from_an_issue(&app).await;
+    let token = unsubscribe_link
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .unwrap()
+        .1
+        .into_owned();
+    let (payload, _) = token.split_once('.').unwrap();
+    unsubscribe_link.set_query(Some(&format!("token={}.{}", payload, "00".repeat(32))));
+
+    let response = app.api_client.post(unsubscribe_link).send().await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 401);
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "confirmed");
+}
+
+#[tokio::test]
+async fn unsubscribing_without_a_token_is_rejected_with_a_400() {
+    let app = spawn_app().await;
+
+    let response = reqwest::get(&format!("{}/subscriptions/unsubscribe", app.address))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn every_issue_carries_one_click_list_unsubscribe_headers() {
+    let app = spawn_app().await;
+    let email_request = deliver_an_issue(&app).await;
+    let unsubscribe_link = app.get_unsubscribe_link(&email_request);
+
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let headers = body["Headers"].as_array().unwrap();
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|h| h["Name"] == name)
+            .unwrap_or_else(|| panic!("Missing {} header", name))["Value"]
+            .as_str()
+            .unwrap()
+            .to_owned()
+    };
+    assert_eq!(
+        header("List-Unsubscribe-Post"),
+        "List-Unsubscribe=One-Click"
+    );
+    let mut list_unsubscribe = reqwest::Url::parse(
+        header("List-Unsubscribe")
+            .trim_start_matches('<')
+            .trim_end_matches('>'),
+    )
+    .unwrap();
+    list_unsubscribe.set_port(Some(app.port)).unwrap();
+    assert_eq!(list_unsubscribe, unsubscribe_link);
+}
+
+#[tokio::test]
+async fn a_one_click_unsubscribe_request_unsubscribes_the_subscriber() {
+    let app = spawn_app().await;
+    let unsubscribe_link = unsubscribe_link_from_an_issue(&app).await;
+
+    // This is what mailbox providers send as described in RFC 8058.
+    let response = reqwest::Client::new()
+        .post(unsubscribe_link)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body("List-Unsubscribe=One-Click")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "unsubscribed");
+}
@@ -0,0 +1,160 @@
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{assert_is_redirect_to, create_confirmed_subscriber, spawn_app, TestApp};
+
+/// Publish an issue to a single confirmed subscriber and return the HTML
+/// body of the email they received, along with the issue id.
+async fn deliver_an_issue(app: &TestApp, body: serde_json::Value) -> (String, uuid::Uuid) {
+    create_confirmed_subscriber(app).await;
+    app.login().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.publish_issue_with(body).await;
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let newsletter_issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .newsletter_issue_id;
+    (
+        body["HtmlBody"].as_str().unwrap().to_owned(),
+        newsletter_issue_id,
+    )
+}
+
+/// The tracking links found in `html`, pointed at the test application.
+fn tracking_links(app: &TestApp, html: &str, prefix: &str) -> Vec<String> {
+    html.match_indices(prefix)
+        .map(|(start, _)| {
+            let token = html[start + prefix.len()..].split('"').next().unwrap();
+            format!("{}{}{}", app.address, prefix, token)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn issues_are_not_tracked_unless_asked_to() {
+    let app = spawn_app().await;
+
+    let (html_body, _) = deliver_an_issue(
+        &app,
+        serde_json::json!({
+            "html_content": r#"<p><a href="https://example.com/article">Read</a></p>"#,
+        }),
+    )
+    .await;
+
+    assert!(html_body.contains(r#"href="https://example.com/article""#));
+    assert!(!html_body.contains("/t/o/"));
+    assert!(!html_body.contains("/t/c/"));
+}
+
+#[tokio::test]
+async fn opens_are_recorded_through_the_tracking_pixel() {
+    let app = spawn_app().await;
+
+    let (html_body, newsletter_issue_id) = deliver_an_issue(
+        &app,
+        serde_json::json!({
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "track_opens": "true",
+        }),
+    )
+    .await;
+    let pixels = tracking_links(&app, &html_body, "/t/o/");
+    assert_eq!(pixels.len(), 1);
+
+    // Opening the email twice still counts the subscriber once.
+    for _ in 0..2 {
+        let response = reqwest::get(&pixels[0]).await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(response.headers()["Content-Type"], "image/gif");
+    }
+
+    let n_events = sqlx::query!("SELECT COUNT(*) AS \"n!\" FROM issue_open_events")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_events, 2);
+    let html_page = app.get_issue_report_html(newsletter_issue_id).await;
+    assert!(html_page.contains("<tr><th>Opened</th><td>1 (100.0% of sent)</td></tr>"));
+    assert!(!html_page.contains("<th>Clicked</th>"));
+}
+
+#[tokio::test]
+async fn clicks_are_recorded_and_redirected_to_the_original_link() {
+    let app = spawn_app().await;
+
+    let (html_body, newsletter_issue_id) = deliver_an_issue(
+        &app,
+        serde_json::json!({
+            "html_content": r#"<p><a href="https://example.com/article?a=1&amp;b=2">Read</a>
+                <a href="{{ unsubscribe_url }}">Leave</a></p>"#,
+            "track_clicks": "true",
+        }),
+    )
+    .await;
+    assert!(!html_body.contains("https://example.com/article"));
+    // The unsubscribe link keeps working without going through a redirect.
+    assert!(html_body.contains(r#"href="http://127.0.0.1/subscriptions/unsubscribe?token="#));
+    let links = tracking_links(&app, &html_body, "/t/c/");
+    assert_eq!(links.len(), 1);
+
+    let response = app.api_client.get(&links[0]).send().await.unwrap();
+    assert_is_redirect_to(&response, "https://example.com/article?a=1&b=2");
+
+    let event = sqlx::query!("SELECT newsletter_issue_id, url FROM issue_click_events")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(event.newsletter_issue_id, newsletter_issue_id);
+    assert_eq!(event.url, "https://example.com/article?a=1&b=2");
+    let html_page = app.get_issue_report_html(newsletter_issue_id).await;
+    assert!(html_page.contains("<tr><th>Clicked</th><td>1 (100.0% of sent)</td></tr>"));
+}
+
+#[tokio::test]
+async fn tampered_tracking_links_are_rejected() {
+    let app = spawn_app().await;
+
+    let (html_body, _) = deliver_an_issue(
+        &app,
+        serde_json::json!({
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "track_opens": "true",
+        }),
+    )
+    .await;
+    let pixel = &tracking_links(&app, &html_body, "/t/o/")[0];
+
+    // A pixel token cannot be used as a redirect.
+    let response = reqwest::get(pixel.replace("/t/o/", "/t/c/")).await.unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+    let response = reqwest::get(format!("{}/t/o/not-a-token", app.address))
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+
+    let n_events = sqlx::query!("SELECT COUNT(*) AS \"n!\" FROM issue_open_events")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_events, 0);
+}